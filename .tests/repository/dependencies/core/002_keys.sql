@@ -0,0 +1,2 @@
+-- depends: core/001_tables.sql
+ALTER TABLE orders ADD CONSTRAINT pk_orders PRIMARY KEY (id);
@@ -0,0 +1,3 @@
+-- Summary report
+-- depends: core/002_keys.sql
+CREATE VIEW summary AS SELECT COUNT(*) AS total FROM orders;
@@ -0,0 +1,2 @@
+-- depends: core/003_missing.sql
+CREATE VIEW detail AS SELECT id FROM orders;
@@ -0,0 +1 @@
+CREATE OR ALTER VIEW v AS SELECT 1 AS x;
@@ -0,0 +1 @@
+CREATE OR ALTER PROCEDURE get_orders AS SELECT 1;
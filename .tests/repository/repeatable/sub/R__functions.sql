@@ -0,0 +1 @@
+CREATE OR ALTER FUNCTION f() RETURNS INT AS BEGIN RETURN 1 END;
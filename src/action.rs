@@ -1,4 +1,4 @@
-use crate::{app::Script, entries::EntryStatus, screen::Mode};
+use crate::{app::Script, dependencies::DependencyGraph, entries::EntryStatus, screen::Mode};
 
 #[allow(unused)]
 #[derive(Debug, Clone, PartialEq)]
@@ -24,24 +24,38 @@ pub enum Action {
     // Directory actions
     DirectoryOpenSelected,
     DirectoryLeave,
+    SwitchRepository,
+    SaveManifest,
+    NewScript,
 
     // Help
     ToggleHelp,
     CloseHelp,
 
+    // Confirmation of the action, described by the message, before it runs
+    Confirm(String, Box<Action>),
+    CloseConfirmation,
+
     // Async actions
     ScriptRun(bool),
-    ScriptRunning(String),
-    ScriptFinished(String, u128, u32),
-    ScriptError(String, String, Option<u32>),
+    RequestRollback,
+    RollbackRun,
+    ScriptRolledBack(Script, u128),
+    ScriptRunning(Script),
+    ScriptFinished(Script, u128, u32),
+    ScriptError(Script, String, Option<u32>),
     CalculateEntryStatus,
-    EntryStatusChanged(String, EntryStatus),
+    EntryStatusChanged(String, String, EntryStatus),
+    RepositoryChanged(String, Vec<String>),
+    DependenciesLoaded(String, DependencyGraph),
 
     // Selection actions
     SelectCurrent,
     SelectAllAfter,
     SelectAllAfterInDirectory,
     SelectAllInDirectory,
+    SelectChangedRepeatables,
+    SelectChangedInGit,
     UnselectAll,
     UnselectCurrent,
     AddSelection(Vec<String>),
@@ -49,4 +63,14 @@ pub enum Action {
     ToggleSelection(Vec<String>),
     SelectionChanged(Vec<String>),
     ScriptHighlighted(Option<Script>),
+
+    // Memory actions
+    MarkSelected(bool),
+    MarkUntilCurrent,
+    RekeyCurrent,
+    RekeyAll,
+    NextMoveCandidate,
+    RequestPruneOrphans,
+    PruneOrphans,
+    ArchiveOrphans,
 }
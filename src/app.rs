@@ -1,6 +1,8 @@
 use crate::{
     action::Action,
     config::Settings,
+    dependencies::DependencyGraph,
+    repository::{ScanRules, ScriptOrdering},
     screen::{Mode, Screen},
     tui,
 };
@@ -13,6 +15,7 @@ use tokio::sync::mpsc;
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone)]
 pub enum ScriptState {
     Finished,
+    RolledBack,
     Running,
     Error,
     None,
@@ -20,102 +23,217 @@ pub enum ScriptState {
 
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone)]
 pub struct Script {
+    pub repository: String,
     pub relative_path: String,
     pub state: ScriptState,
     pub error: Option<String>,
     pub elapsed: Option<u128>,
+    /// What the database returned while running the script
+    pub output: Vec<String>,
 }
 
 impl Script {
-    pub fn none(path: &str) -> Self {
+    pub fn none(repository: &str, path: &str) -> Self {
         Self {
             error: None,
+            repository: repository.into(),
             relative_path: path.into(),
             state: ScriptState::None,
             elapsed: None,
+            output: vec![],
         }
     }
 
     #[allow(dead_code)]
-    pub fn error(path: &str, error: String) -> Self {
+    pub fn error(repository: &str, path: &str, error: String) -> Self {
         Self {
             error: Some(error),
+            repository: repository.into(),
             relative_path: path.into(),
             state: ScriptState::Error,
             elapsed: None,
+            output: vec![],
         }
     }
 
     #[allow(dead_code)]
-    pub fn finished(path: &str, elapsed: u128) -> Self {
+    pub fn finished(repository: &str, path: &str, elapsed: u128) -> Self {
         Self {
             error: None,
+            repository: repository.into(),
             relative_path: path.into(),
             state: ScriptState::Finished,
             elapsed: Some(elapsed),
+            output: vec![],
         }
     }
+
+    /// Whether this is the script at `path` in `repository`, regardless of its run state.
+    pub fn is(&self, repository: &str, path: &str) -> bool {
+        self.repository == repository && self.relative_path == path
+    }
+}
+
+/// How the run queue orders scripts of a single repository.
+pub struct QueueOrder {
+    pub repository: String,
+    pub ordering: ScriptOrdering,
+    pub rules: ScanRules,
+    pub dependencies: DependencyGraph,
 }
 
 pub struct AppState {
     pub selected: Vec<Script>,
+    orders: Vec<QueueOrder>,
+    /// Set when the queue was loaded from a manifest, whose order is kept as it is
+    manual_order: bool,
 }
 
 impl AppState {
-    pub fn new() -> Self {
-        Self { selected: vec![] }
+    /// Takes the ordering of every repository in the session, the queue runs
+    /// repositories in the order given here.
+    pub fn new(orders: Vec<QueueOrder>) -> Self {
+        Self {
+            selected: vec![],
+            orders,
+            manual_order: false,
+        }
     }
 
-    pub fn add(&mut self, script: String) {
-        if !self.selected.iter().any(|s| s.relative_path == script) {
-            self.selected.push(Script::none(&script));
-            self.selected.sort()
+    /// Replaces the queue with scripts given as repository names and paths, keeping their order.
+    pub fn load(&mut self, scripts: &[(String, String)]) {
+        self.selected = scripts
+            .iter()
+            .map(|(repository, path)| Script::none(repository, path))
+            .collect();
+        self.manual_order = true;
+    }
+
+    /// Empties the queue, which is sorted again from now on.
+    pub fn clear(&mut self) {
+        self.selected.clear();
+        self.manual_order = false;
+    }
+
+    pub fn set_dependencies(&mut self, repository: &str, dependencies: DependencyGraph) {
+        if let Some(order) = self.orders.iter_mut().find(|o| o.repository == repository) {
+            order.dependencies = dependencies;
+            self.sort()
         }
     }
 
-    pub fn remove(&mut self, script: String) {
-        self.selected.retain(|s| s.relative_path != script);
-        self.selected.sort()
+    /// Orders the run queue the same way the repositories order their scripts,
+    /// with repeatable scripts of each repository after its versioned ones
+    /// and every script after the scripts it depends on.
+    pub fn sort(&mut self) {
+        if self.manual_order {
+            return;
+        }
+
+        let orders = &self.orders;
+        let position = |repository: &str| {
+            orders
+                .iter()
+                .position(|order| order.repository == repository)
+                .unwrap_or(usize::MAX)
+        };
+        let repeatable = |script: &Script| {
+            orders
+                .iter()
+                .find(|order| order.repository == script.repository)
+                .is_some_and(|order| order.rules.is_repeatable(&script.relative_path))
+        };
+
+        self.selected.sort_by(|a, b| {
+            position(&a.repository)
+                .cmp(&position(&b.repository))
+                .then_with(|| repeatable(a).cmp(&repeatable(b)))
+                .then_with(|| {
+                    let ordering = orders
+                        .iter()
+                        .find(|order| order.repository == a.repository)
+                        .map(|order| order.ordering)
+                        .unwrap_or_default();
+                    ordering.compare_paths(&a.relative_path, &b.relative_path)
+                })
+        });
+
+        let mut sorted = Vec::with_capacity(self.selected.len());
+        let mut remaining = std::mem::take(&mut self.selected).into_iter().peekable();
+        while let Some(first) = remaining.next() {
+            let mut group = vec![first];
+            while let Some(next) = remaining.next_if(|s| s.repository == group[0].repository) {
+                group.push(next);
+            }
+
+            match orders.iter().find(|o| o.repository == group[0].repository) {
+                Some(order) => {
+                    let paths: Vec<&str> = group.iter().map(|s| s.relative_path.as_str()).collect();
+                    let mut group: Vec<Option<Script>> = group.iter().cloned().map(Some).collect();
+                    sorted.extend(
+                        order
+                            .dependencies
+                            .order(&paths)
+                            .into_iter()
+                            .filter_map(|index| group[index].take()),
+                    );
+                }
+                None => sorted.extend(group),
+            }
+        }
+        self.selected = sorted;
     }
 
-    pub fn remove_many(&mut self, script: &[String]) {
-        self.selected.retain(|s| !script.contains(&s.relative_path));
-        self.selected.sort()
+    pub fn contains(&self, repository: &str, script: &str) -> bool {
+        self.selected.iter().any(|s| s.is(repository, script))
     }
 
-    pub fn toggle(&mut self, scripts: String) {
-        if self.selected.iter().any(|s| s.relative_path == scripts) {
-            self.selected.retain(|s| s.relative_path != scripts);
+    pub fn add(&mut self, repository: &str, script: String) {
+        if !self.contains(repository, &script) {
+            self.selected.push(Script::none(repository, &script));
+            self.sort()
+        }
+    }
+
+    pub fn remove(&mut self, repository: &str, script: String) {
+        self.selected.retain(|s| !s.is(repository, &script));
+        self.sort()
+    }
+
+    pub fn remove_many(&mut self, repository: &str, script: &[String]) {
+        self.selected
+            .retain(|s| s.repository != repository || !script.contains(&s.relative_path));
+        self.sort()
+    }
+
+    pub fn toggle(&mut self, repository: &str, scripts: String) {
+        if self.contains(repository, &scripts) {
+            self.selected.retain(|s| !s.is(repository, &scripts));
         } else {
-            self.add(scripts);
+            self.add(repository, scripts);
         }
-        self.selected.sort()
+        self.sort()
     }
 
-    pub fn toggle_many(&mut self, scripts: &[String]) {
-        if self
-            .selected
-            .iter()
-            .any(|s| scripts.contains(&s.relative_path))
-        {
-            self.selected
-                .retain(|s| !scripts.contains(&s.relative_path));
+    pub fn toggle_many(&mut self, repository: &str, scripts: &[String]) {
+        if scripts.iter().any(|s| self.contains(repository, s)) {
+            self.remove_many(repository, scripts);
         } else {
-            self.add_many(scripts);
+            self.add_many(repository, scripts);
         }
 
-        self.selected.sort()
+        self.sort()
     }
 
-    pub fn add_many(&mut self, scripts: &[String]) {
+    pub fn add_many(&mut self, repository: &str, scripts: &[String]) {
         let new_items: Vec<Script> = scripts
             .iter()
-            .filter(|s| !self.selected.iter().any(|r| r.relative_path == **s))
-            .map(|s| Script::none(s))
+            .filter(|s| !self.contains(repository, s))
+            .map(|s| Script::none(repository, s))
             .collect();
 
         self.selected.extend(new_items);
-        self.selected.sort()
+        self.sort()
     }
 }
 
@@ -128,6 +246,8 @@ pub struct App {
     pub screens: Vec<Screen>,
     pub config: Settings,
     pub state: AppState,
+    /// Action waiting for the user to confirm it
+    pub confirmation: Option<Action>,
 }
 
 impl App {
@@ -139,8 +259,21 @@ impl App {
             frame_rate: 30.0,
             tick_rate: 1.0,
             screens,
+            state: AppState::new(
+                config
+                    .named_repositories()
+                    .into_iter()
+                    .map(|named| QueueOrder {
+                        ordering: named.repository.ordering,
+                        // Invalid patterns are reported when the repositories are opened
+                        rules: ScanRules::from_settings(&named.repository).unwrap_or_default(),
+                        dependencies: DependencyGraph::default(),
+                        repository: named.name,
+                    })
+                    .collect(),
+            ),
             config,
-            state: AppState::new(),
+            confirmation: None,
         }
     }
 
@@ -179,6 +312,16 @@ impl App {
                     tui::Event::Render => action_tx.send(Action::Render)?,
                     tui::Event::Resize(x, y) => action_tx.send(Action::Resize(x, y))?,
                     tui::Event::SwitchMode(mode) => action_tx.send(Action::SwitchMode(mode))?,
+                    // Any key but `y` cancels the pending action
+                    tui::Event::Key(key) if self.confirmation.is_some() => {
+                        if let (KeyCode::Char('y'), Some(confirmed)) =
+                            (key.code, self.confirmation.take())
+                        {
+                            action_tx.send(confirmed)?;
+                        }
+                        self.confirmation = None;
+                        action_tx.send(Action::CloseConfirmation)?;
+                    }
                     tui::Event::Key(key) => match (self.current_screen, key.code) {
                         (_, KeyCode::Char('z')) if key.modifiers == KeyModifiers::CONTROL => {
                             action_tx.send(Action::Suspend)?
@@ -187,6 +330,25 @@ impl App {
                             action_tx.send(Action::Quit)?
                         }
                         (_, KeyCode::Char('q')) => action_tx.send(Action::Quit)?,
+                        (Mode::Orphans, KeyCode::Char('p')) => {
+                            action_tx.send(Action::RequestPruneOrphans)?
+                        }
+                        (Mode::Orphans, KeyCode::Char('a')) => {
+                            action_tx.send(Action::ArchiveOrphans)?
+                        }
+                        (Mode::ScriptRunner, KeyCode::Char('u')) => {
+                            action_tx.send(Action::RequestRollback)?
+                        }
+                        (Mode::ScriptRunner, KeyCode::Char('m')) => {
+                            action_tx.send(Action::SaveManifest)?
+                        }
+                        (Mode::FileChooser, KeyCode::Char('c')) => {
+                            action_tx.send(Action::NewScript)?
+                        }
+                        (_, KeyCode::Char('w')) => action_tx.send(Action::SwitchRepository)?,
+                        (_, KeyCode::Char('o')) => {
+                            action_tx.send(Action::SwitchMode(Mode::Orphans))?
+                        }
                         (_, KeyCode::Char('r')) => action_tx.send(Action::ScriptRun(false))?,
                         (_, KeyCode::Char('R')) => action_tx.send(Action::ScriptRun(true))?,
                         (_, KeyCode::Char(' ')) => action_tx.send(Action::SelectCurrent)?,
@@ -195,8 +357,18 @@ impl App {
                         }
                         (_, KeyCode::Char('S')) => action_tx.send(Action::SelectAllAfter)?,
                         (_, KeyCode::Char('d')) => action_tx.send(Action::SelectAllInDirectory)?,
+                        (_, KeyCode::Char('g')) => action_tx.send(Action::SelectChangedInGit)?,
+                        (_, KeyCode::Char('e')) => {
+                            action_tx.send(Action::SelectChangedRepeatables)?
+                        }
                         (_, KeyCode::Char('x')) => action_tx.send(Action::UnselectCurrent)?,
                         (_, KeyCode::Char('X')) => action_tx.send(Action::UnselectAll)?,
+                        (_, KeyCode::Char('a')) => action_tx.send(Action::MarkSelected(true))?,
+                        (_, KeyCode::Char('A')) => action_tx.send(Action::MarkUntilCurrent)?,
+                        (_, KeyCode::Char('n')) => action_tx.send(Action::MarkSelected(false))?,
+                        (_, KeyCode::Char('k')) => action_tx.send(Action::RekeyCurrent)?,
+                        (_, KeyCode::Char('K')) => action_tx.send(Action::RekeyAll)?,
+                        (_, KeyCode::Char('l')) => action_tx.send(Action::NextMoveCandidate)?,
                         (_, KeyCode::Char('h')) => action_tx.send(Action::ToggleHelp)?,
                         (_, KeyCode::Up) => action_tx.send(Action::CursorUp)?,
                         (_, KeyCode::Down) => action_tx.send(Action::CursorDown)?,
@@ -207,7 +379,7 @@ impl App {
                         (Mode::FileChooser, KeyCode::Tab) => {
                             action_tx.send(Action::SwitchMode(Mode::ScriptRunner))?
                         }
-                        (Mode::ScriptRunner, KeyCode::Tab) => {
+                        (Mode::ScriptRunner | Mode::Orphans, KeyCode::Tab) => {
                             action_tx.send(Action::SwitchMode(Mode::FileChooser))?
                         }
                         _ => {}
@@ -236,6 +408,11 @@ impl App {
                     Action::Suspend => self.suspend = true,
                     Action::Resume => self.suspend = false,
                     Action::SwitchMode(mode) => self.current_screen = mode,
+                    Action::Confirm(_, ref confirmed) => {
+                        self.confirmation = Some(*confirmed.clone())
+                    }
+                    // The list asks for the script on the plain terminal and runs the editor
+                    Action::NewScript => tui.exit()?,
                     Action::Resize(w, h) => {
                         tui.resize(Rect::new(0, 0, w, h))?;
                         let screen = self
@@ -276,7 +453,13 @@ impl App {
                     _ => {}
                 }
 
-                if let Action::EntryStatusChanged(_, _) = action {
+                if matches!(
+                    action,
+                    Action::EntryStatusChanged(_, _, _)
+                        | Action::CalculateEntryStatus
+                        | Action::RepositoryChanged(..)
+                        | Action::DependenciesLoaded(_, _)
+                ) {
                     for screen in self.screens.iter_mut() {
                         for component in screen.components.iter_mut() {
                             if action != Action::Tick && action != Action::Render {
@@ -308,6 +491,13 @@ impl App {
                         }
                     }
                 }
+
+                if action == Action::NewScript {
+                    tui = tui::Tui::new()?
+                        .tick_rate(self.tick_rate)
+                        .frame_rate(self.frame_rate);
+                    tui.enter()?;
+                }
             }
             if self.suspend {
                 tui.suspend()?;
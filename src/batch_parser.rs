@@ -11,7 +11,9 @@ impl BatchParser {
         let mut comment_skipping = false;
         let mut go_detected = false;
 
-        for (i, ch) in sql.chars().enumerate() {
+        let chars: Vec<char> = sql.chars().collect();
+
+        for (i, &ch) in chars.iter().enumerate() {
             if ch == '\'' && !comment_skipping {
                 string_skipping = !string_skipping;
             }
@@ -31,7 +33,7 @@ impl BatchParser {
                     go_detected = true; // Potential start of "GO"
                 } else if go_detected
                     && ch == 'O'
-                    && sql.chars().nth(i + 1).map_or(true, |c| c.is_whitespace())
+                    && chars.get(i + 1).is_none_or(|c| c.is_whitespace())
                 {
                     // Confirmed "GO" with whitespaces around, split batch
                     batches.push(current_batch.clone().trim_end_matches('G').to_owned());
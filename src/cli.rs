@@ -1,8 +1,13 @@
+use std::path::PathBuf;
+
 use clap::{Args, Parser, Subcommand};
 
 use crate::{
     config::Settings,
+    credentials,
     db::{Authentication, Database},
+    report::{Report, ReportFormat},
+    status::OutputFormat,
     ArgumentsError,
 };
 
@@ -19,6 +24,14 @@ pub struct SquealMateArgs {
 
     #[command(flatten)]
     pub connection: ConnectionArgs,
+
+    /// Name of the configured repository to work with (defaults to the first one)
+    #[arg(long, short = 'r', global = true)]
+    pub repository: Option<String>,
+
+    /// Name of the configured profile to work with (defaults to `profile` of the config)
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -32,7 +45,7 @@ pub struct ConnectionArgs {
     /// Username used to log into db
     #[arg(long, short = 'u')]
     pub username: Option<String>,
-    /// Password used to log into db
+    /// Password used to log into db, prefer `password_env` or `password_command` of the config
     #[arg(long, short = 'p')]
     pub password: Option<String>,
     /// Name of the database to connect to
@@ -79,11 +92,14 @@ impl ConnectionArgs {
                 .or_else(|| settings.database.username.clone())
                 .ok_or(ArgumentsError::MissingUsername)?;
 
-            let password = self
-                .password
-                .clone()
-                .or_else(|| settings.database.password.clone())
-                .ok_or(ArgumentsError::MissingPassword)?;
+            let password = match self.password.clone() {
+                Some(password) => password,
+                None => credentials::resolve(&settings.database)
+                    .map_err(ArgumentsError::Credentials)?
+                    .ok_or_else(|| {
+                        ArgumentsError::MissingPassword(format!("{}@{}:{}", username, server, port))
+                    })?,
+            };
 
             Authentication::SqlServer { username, password }
         };
@@ -97,15 +113,143 @@ impl ConnectionArgs {
     }
 }
 
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// Writes the results of the run to this file, overriding `[report]` of the config
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+    /// Format of the report, picked from its extension by default (.xml, .json, .md)
+    #[arg(long, value_enum)]
+    pub report_format: Option<ReportFormat>,
+}
+
+impl ReportArgs {
+    /// Report asked for on the command line, or the configured one.
+    pub fn merge(&self, settings: &Settings) -> Option<Report> {
+        let mut report = match self.report {
+            Some(ref path) => Report {
+                path: path.clone(),
+                format: None,
+            },
+            None => settings.report.clone()?,
+        };
+        if self.report_format.is_some() {
+            report.format = self.report_format;
+        }
+        Some(report)
+    }
+}
+
+/// Scripts picked on the command line.
+#[derive(Debug, Args)]
+pub struct SelectionArgs {
+    /// Scripts to run, relative to the repository root, or glob patterns matching them
+    pub scripts: Vec<String>,
+    /// Runs every script not applied yet or changed since it was
+    #[arg(long)]
+    pub pending: bool,
+    /// Runs this script and every script after it
+    #[arg(long)]
+    pub after: Option<String>,
+    /// Runs every script added or modified in git since this ref
+    #[arg(long)]
+    pub since: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub selection: SelectionArgs,
+    /// Continues with the next script after an error
+    #[arg(long)]
+    pub skip_errors: bool,
+    /// Prints what would be executed, the same as `plan`, without touching the database
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Command {
     /// Shows application info and configuration for the current system
     Config,
     /// Starts the migrations explorer
-    Migrations,
+    Migrations {
+        /// Manifest listing scripts to queue in the given order, saving the queue writes it back
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+        #[command(flatten)]
+        report: ReportArgs,
+    },
     /// Helps set up the config file
     #[command(name = "init")]
     Initialize,
+    /// Marks scripts as applied without running them, or resets their history
+    Mark {
+        /// Scripts to mark, relative to the repository root
+        scripts: Vec<String>,
+        /// Marks every script up to and including this one
+        #[arg(long)]
+        until: Option<String>,
+        /// Marks the scripts as not applied instead
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Runs scripts without the explorer, exiting with an error when any of them fails
+    Run(RunArgs),
+    /// Prints the scripts and batches `run` would execute, without touching the database
+    Plan(SelectionArgs),
+    /// Reports which scripts are applied and which still have to run
+    Status {
+        /// Lists every script instead of only those still having to run
+        #[arg(long)]
+        all: bool,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Creates the next script from the template and opens it in the editor
+    New {
+        /// What the script does, also used for its file name
+        #[arg(required = true)]
+        description: Vec<String>,
+        /// Directory relative to the repository root, defaults to that of the last script
+        #[arg(long)]
+        dir: Option<String>,
+        /// Ticket written to the header
+        #[arg(long)]
+        ticket: Option<String>,
+        /// Only prints the path of the script without opening the editor
+        #[arg(long)]
+        no_edit: bool,
+    },
+    /// Checks scripts for common mistakes, exiting with an error when any rule set to error fails
+    Lint {
+        /// Scripts to check, relative to the repository root, or glob patterns matching them.
+        /// Every script is checked when none is given
+        scripts: Vec<String>,
+        /// Fails on warnings as well
+        #[arg(long)]
+        deny_warnings: bool,
+    },
+    /// Lists scripts added or modified in git since a ref, in run order
+    Changed {
+        /// Ref to compare against, defaults to `base_ref` of the repository
+        #[arg(long)]
+        since: Option<String>,
+        /// Ref to compare up to instead of the working tree
+        #[arg(long)]
+        until: Option<String>,
+    },
+    /// Lists history records of scripts that no longer exist in the repository
+    Orphans {
+        /// Deletes the orphaned records
+        #[arg(long, conflicts_with = "archive")]
+        prune: bool,
+        /// Moves the orphaned records to the archive table
+        #[arg(long)]
+        archive: bool,
+    },
 }
 
 #[test]
@@ -177,3 +321,25 @@ fn simple_positive() {
         panic!("simple_positive: Cannot parse correct result");
     }
 }
+
+#[test]
+fn missing_password_login() {
+    let mut settings = Settings::default();
+    settings.database.server = Some("dbserver".to_string());
+    settings.database.username = Some("cli".to_string());
+    settings.database.name = Some("AppDb".to_string());
+
+    // The login asked for is the one the connection uses, overrides included
+    let args = ConnectionArgs {
+        server: Some("uat-sql".to_string()),
+        port: None,
+        username: Some("deploy".to_string()),
+        password: None,
+        name: None,
+        is_integrated: None,
+    };
+    assert!(matches!(
+        args.merge(&settings),
+        Err(ArgumentsError::MissingPassword(login)) if login == "deploy@uat-sql:1433"
+    ));
+}
@@ -0,0 +1,67 @@
+use tui_popup::Popup;
+
+use color_eyre::eyre::Result;
+use ratatui::prelude::*;
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::Component;
+use crate::{action::Action, app::AppState, config::Settings, tui::Frame};
+
+/// Asks before an action that cannot be undone, which the app runs once `y` is pressed.
+pub struct Confirm {
+    command_tx: Option<UnboundedSender<Action>>,
+    config: Settings,
+    message: Option<String>,
+}
+
+impl Confirm {
+    pub fn new() -> Self {
+        Self {
+            command_tx: None,
+            config: Settings::default(),
+            message: None,
+        }
+    }
+}
+
+impl Component for Confirm {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Settings) -> Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, _: &mut AppState, action: Action) -> Result<Option<Action>> {
+        match action {
+            Action::Confirm(message, _) => self.message = Some(message),
+            Action::CloseConfirmation | Action::SwitchMode(_) => self.message = None,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, _area: Rect, _: &AppState) -> Result<()> {
+        if let Some(ref message) = self.message {
+            let mut text: Text = message
+                .lines()
+                .map(|line| Line::raw(format!(" {} ", line)))
+                .collect();
+            text.push_line(Line::default());
+            text.push_line(Line::styled(
+                " y: confirm, any other key: cancel ",
+                Style::new().bold(),
+            ));
+
+            let popup = Popup::new(text)
+                .title("Confirm")
+                .style(Style::new().black().on_light_red());
+
+            f.render_widget(&popup, f.area());
+        }
+        Ok(())
+    }
+}
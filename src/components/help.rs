@@ -30,16 +30,66 @@ impl<'a> Help<'a> {
             ("Space".to_string(), "Toggle file selection".to_string()),
             (
                 "s".to_string(),
-                "Select all after cursor in current directory".to_string(),
+                "Select all from cursor on in current directory".to_string(),
             ),
-            ("S".to_string(), "Select all after cursor".to_string()),
+            ("S".to_string(), "Select all from cursor on".to_string()),
             (
                 "d".to_string(),
                 "Select all in current directory".to_string(),
             ),
             ("x".to_string(), "Unselect current file".to_string()),
             ("X".to_string(), "Unselect all in directory".to_string()),
+            (
+                "a".to_string(),
+                "Mark selected scripts as applied".to_string(),
+            ),
+            (
+                "A".to_string(),
+                "Mark all scripts up to cursor as applied".to_string(),
+            ),
+            (
+                "n".to_string(),
+                "Mark selected scripts as not applied".to_string(),
+            ),
+            (
+                "k".to_string(),
+                "Carry over history of moved script".to_string(),
+            ),
+            (
+                "K".to_string(),
+                "Carry over history of all moved scripts".to_string(),
+            ),
+            (
+                "l".to_string(),
+                "Show next place a script may have moved from".to_string(),
+            ),
+            ("o".to_string(), "Show orphaned history".to_string()),
+            (
+                "p / a".to_string(),
+                "Prune / archive orphaned history".to_string(),
+            ),
+            (
+                "g".to_string(),
+                "Select scripts changed in git since the base ref".to_string(),
+            ),
+            (
+                "e".to_string(),
+                "Select changed repeatable scripts".to_string(),
+            ),
             ("r".to_string(), "Run selected scripts".to_string()),
+            (
+                "u".to_string(),
+                "Roll back applied scripts of the queue".to_string(),
+            ),
+            (
+                "m".to_string(),
+                "Save selected scripts as a manifest".to_string(),
+            ),
+            ("w".to_string(), "Switch to the next repository".to_string()),
+            (
+                "c".to_string(),
+                "Create a new script in current directory".to_string(),
+            ),
             (
                 "R".to_string(),
                 "Run selected scripts, skipping errors".to_string(),
@@ -1,8 +1,5 @@
-use std::path::PathBuf;
-
 use color_eyre::eyre::{self, Ok, Result};
 
-use crc::{Crc, CRC_32_ISO_HDLC};
 use ratatui::{
     prelude::*,
     widgets::{block::Position, *},
@@ -11,37 +8,188 @@ use tokio::sync::mpsc::UnboundedSender;
 
 use super::Component;
 use crate::{
-    action::Action, config::Settings, entries::EntryStatus, repository::Repository,
-    script_memory::ScriptDatabase, tui::Frame, utils::send_through_channel,
+    action::Action,
+    config::Settings,
+    dependencies::{DependencyError, DependencyGraph},
+    entries::EntryStatus,
+    git::GitStatus,
+    lint::Severity,
+    repository::Repository,
+    scaffold,
+    script_memory::checksum,
+    tui::Frame,
+    utils::send_through_channel,
+    watcher::{watch_repository, RepositoryWatcher},
+    workspace::Workspace,
 };
 use crate::{app::AppState, entries::ListEntry};
 pub struct List {
-    base: PathBuf,
     command_tx: Option<UnboundedSender<Action>>,
     config: Settings,
     state: ListState,
-    repository: Repository,
+    repositories: Vec<Repository>,
+    workspaces: Vec<Workspace>,
+    current: usize,
     entries: Vec<ListEntry>,
-    script_memory: ScriptDatabase,
+    /// Watchers of every repository on disk, so the ones not shown are kept indexed too
+    watchers: Vec<RepositoryWatcher>,
+    /// Dependencies declared in each repository, in the same order as `repositories`
+    dependencies: Vec<DependencyGraph>,
+    /// Missing or cyclic dependencies in the current repository
+    problems: Vec<DependencyError>,
+    /// Script whose ambiguous move is looked at, and the index of the candidate shown
+    move_candidate: (String, usize),
 }
 
 impl List {
+    /// Takes the repositories of the session along with their workspaces, in the same order,
+    /// and the index of the one shown first.
     pub fn new(
-        repository: Repository,
-        base: PathBuf,
-        script_memory: ScriptDatabase,
+        repositories: Vec<Repository>,
+        workspaces: Vec<Workspace>,
+        current: usize,
     ) -> Result<Self> {
         Ok(Self {
             state: ListState::default().with_selected(Some(0)),
             command_tx: None,
             config: Settings::default(),
-            entries: repository.read_entries_in_current_directory()?,
-            script_memory,
-            repository,
-            base,
+            entries: repositories[current].read_entries_in_current_directory()?,
+            problems: vec![],
+            dependencies: vec![DependencyGraph::default(); repositories.len()],
+            repositories,
+            workspaces,
+            current,
+            watchers: vec![],
+            move_candidate: (String::new(), 0),
         })
     }
 
+    fn repository(&self) -> &Repository {
+        &self.repositories[self.current]
+    }
+
+    fn repository_mut(&mut self) -> &mut Repository {
+        &mut self.repositories[self.current]
+    }
+
+    fn workspace(&self) -> &Workspace {
+        &self.workspaces[self.current]
+    }
+
+    /// Shows the next repository of the session, wrapping around after the last one.
+    pub fn switch_repository(&mut self) -> eyre::Result<()> {
+        if self.repositories.len() < 2 {
+            return Ok(());
+        }
+
+        self.current = (self.current + 1) % self.repositories.len();
+        self.entries = self.repository().read_entries_in_current_directory()?;
+        self.problems = self.dependencies[self.current].problems();
+
+        if self.entries.is_empty() {
+            self.state.select(None)
+        } else {
+            self.state.select(Some(0))
+        }
+
+        send_through_channel(&self.command_tx, Action::CalculateEntryStatus);
+
+        Ok(())
+    }
+
+    /// Re-reads dependencies declared in the repository at `index` in the background once
+    /// it is indexed, the graph comes back with `Action::DependenciesLoaded`.
+    fn load_dependencies(&self, index: usize) {
+        let channel = self.command_tx.clone();
+        let repository = self.repositories[index].clone();
+        let name = self.workspaces[index].name.clone();
+        tokio::task::spawn_blocking(move || {
+            repository.wait_for_index();
+            let graph = repository.dependency_graph();
+            send_through_channel(&channel, Action::DependenciesLoaded(name, graph));
+        });
+    }
+
+    fn dependencies_loaded(&mut self, name: &str, graph: DependencyGraph) {
+        let Some(index) = self.workspaces.iter().position(|w| w.name == name) else {
+            return;
+        };
+        if index == self.current {
+            self.problems = graph.problems();
+            for problem in self.problems.iter() {
+                log::warn!("{}", problem);
+            }
+        }
+        self.dependencies[index] = graph;
+    }
+
+    fn share_dependencies(&self, state: &mut AppState) {
+        for (workspace, graph) in self.workspaces.iter().zip(self.dependencies.iter()) {
+            state.set_dependencies(&workspace.name, graph.clone());
+        }
+    }
+
+    /// Adds dependencies of the selected scripts that were not applied yet.
+    fn pull_dependencies(&self, state: &mut AppState) {
+        let workspace = self.workspace();
+        let graph = &self.dependencies[self.current];
+        let is_pending = |script: &str| {
+            workspace
+                .script_memory
+                .is_pending(&workspace.source, &workspace.rules, script)
+                .unwrap_or_else(|e| {
+                    log::error!("Dependency {} not queued: {}", script, e);
+                    false
+                })
+        };
+
+        let mut missing: Vec<String> = vec![];
+        for script in state
+            .selected
+            .iter()
+            .filter(|s| s.repository == workspace.name)
+        {
+            for requirement in graph.requirements(&script.relative_path) {
+                if !missing.contains(&requirement)
+                    && !state.contains(&workspace.name, &requirement)
+                    && is_pending(&requirement)
+                {
+                    missing.push(requirement);
+                }
+            }
+        }
+
+        state.add_many(&workspace.name, &missing);
+    }
+
+    fn watch(&mut self) {
+        let Some(ref tx) = self.command_tx else {
+            return;
+        };
+
+        self.watchers = self
+            .workspaces
+            .iter()
+            .filter_map(|workspace| {
+                // Archives are read-only, there is nothing to watch
+                let root = workspace.source.directory()?;
+                let name = workspace.name.clone();
+                let rules = workspace.rules.clone();
+                match watch_repository(name, root.to_path_buf(), rules, tx.clone()) {
+                    core::result::Result::Ok(watcher) => Some(watcher),
+                    Err(e) => {
+                        log::error!(
+                            "Cannot watch repository {} for changes: {}",
+                            workspace.name,
+                            e
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+    }
+
     pub fn cursor_up(&mut self) {
         if let Some(position) = self.state.selected() {
             if position > 0 {
@@ -83,8 +231,8 @@ impl List {
             ..
         }) = entry
         {
-            self.repository.open_directory(&name);
-            self.entries = self.repository.read_entries_in_current_directory()?;
+            self.repository_mut().open_directory(&name);
+            self.entries = self.repository().read_entries_in_current_directory()?;
 
             let channel: Option<UnboundedSender<Action>> = self.command_tx.clone();
 
@@ -103,9 +251,9 @@ impl List {
     }
 
     pub fn leave_current_directory(&mut self) -> eyre::Result<()> {
-        let old_dir = self.repository.leave_directory();
+        let old_dir = self.repository_mut().leave_directory();
         if let Some(old_dir) = old_dir {
-            self.entries = self.repository.read_entries_in_current_directory()?;
+            self.entries = self.repository().read_entries_in_current_directory()?;
             self.state.select(Some(0));
 
             let channel: Option<UnboundedSender<Action>> = self.command_tx.clone();
@@ -137,11 +285,13 @@ impl List {
 
         let entry = entry.unwrap();
 
+        let repository = &self.workspace().name;
+
         if entry.is_directory {
-            let items = self.repository.get_children(entry.relative_path);
-            state.toggle_many(&items);
+            let items = self.repository().get_children(entry.relative_path);
+            state.toggle_many(repository, &items);
         } else {
-            state.toggle(entry.relative_path);
+            state.toggle(repository, entry.relative_path);
         }
     }
 
@@ -154,16 +304,18 @@ impl List {
 
         let entry = entry.unwrap();
 
+        let repository = &self.workspace().name;
+
         if entry.is_directory {
-            let items = self.repository.get_children(entry.relative_path);
-            state.remove_many(&items);
+            let items = self.repository().get_children(entry.relative_path);
+            state.remove_many(repository, &items);
         } else {
-            state.remove(entry.relative_path);
+            state.remove(repository, entry.relative_path);
         }
     }
 
     pub fn unselect_all(&mut self, state: &mut AppState) {
-        state.selected.clear()
+        state.clear()
     }
 
     pub fn select_all_after(&mut self, state: &mut AppState) {
@@ -175,9 +327,9 @@ impl List {
 
         let entry = entry.unwrap();
 
-        let entries = self.repository.read_files_after(&entry.name);
+        let entries = self.repository().read_files_after(&entry.name);
 
-        state.add_many(&entries);
+        state.add_many(&self.workspace().name, &entries);
     }
 
     pub fn select_all_after_in_directory(&mut self, state: &mut AppState) {
@@ -190,20 +342,266 @@ impl List {
         let entry = entry.unwrap();
 
         let entries = self
-            .repository
+            .repository()
             .read_files_after_in_directory(&entry.name)
             .unwrap_or_default();
 
-        state.add_many(&entries);
+        state.add_many(&self.workspace().name, &entries);
+    }
+
+    pub fn mark_selected(&mut self, state: &mut AppState, applied: bool) {
+        for workspace in self.workspaces.iter() {
+            let scripts: Vec<String> = state
+                .selected
+                .iter()
+                .filter(|s| s.repository == workspace.name)
+                .map(|s| s.relative_path.clone())
+                .collect();
+
+            mark(workspace, &scripts, applied);
+
+            if applied {
+                state.remove_many(&workspace.name, &scripts);
+            }
+        }
+
+        send_through_channel(&self.command_tx, Action::CalculateEntryStatus);
+    }
+
+    pub fn mark_until_current(&mut self, state: &mut AppState) {
+        let entry = self.get_selection().cloned();
+
+        if entry.is_none() {
+            return;
+        };
+
+        let entry = entry.unwrap();
+
+        let scripts = self.repository().read_files_until(&entry.relative_path);
+
+        mark(self.workspace(), &scripts, true);
+
+        state.remove_many(&self.workspace().name, &scripts);
+
+        send_through_channel(&self.command_tx, Action::CalculateEntryStatus);
+    }
+
+    /// Carries over the history of the highlighted script when it was moved from a single
+    /// place. Candidates of ambiguous moves are shown below the list instead.
+    pub fn rekey_current(&mut self) {
+        let entry = self.get_selection().cloned();
+
+        if let Some(ListEntry {
+            relative_path,
+            status: EntryStatus::Moved(candidates),
+            ..
+        }) = entry
+        {
+            let old = &candidates[self.move_candidate_of(&relative_path) % candidates.len()];
+            if let Err(e) = self.workspace().script_memory.rekey(old, &relative_path) {
+                log::error!("Failed to re-key script {} : {}", old, e);
+            }
+
+            send_through_channel(&self.command_tx, Action::CalculateEntryStatus);
+        }
+    }
+
+    /// Index of the candidate of an ambiguous move of `script` shown to the user.
+    fn move_candidate_of(&self, script: &str) -> usize {
+        match self.move_candidate {
+            (ref path, index) if path == script => index,
+            _ => 0,
+        }
+    }
+
+    /// Shows the next candidate the highlighted script may have been moved from.
+    pub fn next_move_candidate(&mut self) {
+        if let Some(entry) = self.get_selection() {
+            let index = self.move_candidate_of(&entry.relative_path) + 1;
+            self.move_candidate = (entry.relative_path.clone(), index);
+        }
+    }
+
+    /// Carries over the history of every script moved from a single place in the background,
+    /// ambiguous moves are left for the user to pick with [`List::rekey_current`].
+    pub fn rekey_all(&self) {
+        let channel = self.command_tx.clone();
+        let workspace = self.workspace().clone();
+        let repository = self.repository().clone();
+        tokio::task::spawn_blocking(move || {
+            let memory = &workspace.script_memory;
+
+            for script in repository.get_children("".into()) {
+                let result = workspace
+                    .source
+                    .read(&script)
+                    .map_err(eyre::Report::from)
+                    .and_then(|content| {
+                        let crc = checksum(&content);
+                        if memory.get_file_status(&script, &crc)? != EntryStatus::NeverStarted {
+                            return Ok(());
+                        }
+                        if let [old] = memory.find_moved(&workspace.source, crc)?.as_slice() {
+                            memory.rekey(old, &script)?;
+                        }
+                        Ok(())
+                    });
+
+                if let Err(e) = result {
+                    log::error!("Failed to re-key script {} : {}", script, e);
+                }
+            }
+
+            send_through_channel(&channel, Action::CalculateEntryStatus);
+        });
+    }
+
+    /// Re-reads the current directory after the repository changed on disk, keeping the
+    /// cursor on the same entry and recalculating status only for new or changed entries.
+    pub fn refresh(&mut self, changed: &[String]) -> eyre::Result<()> {
+        let highlighted = self.get_selection().map(|e| e.relative_path.clone());
+        let old_entries = std::mem::take(&mut self.entries);
+
+        self.entries = self.repository().read_entries_in_current_directory()?;
+
+        let mut outdated = vec![];
+        for entry in self.entries.iter_mut() {
+            let old = old_entries
+                .iter()
+                .find(|old| old.relative_path == entry.relative_path);
+
+            match old {
+                Some(old) if !changed.contains(&entry.relative_path) => {
+                    entry.status = old.status.clone()
+                }
+                _ => outdated.push(entry.clone()),
+            }
+        }
+
+        let position = highlighted
+            .and_then(|path| self.entries.iter().position(|e| e.relative_path == path))
+            .or_else(|| {
+                self.state
+                    .selected()
+                    .map(|p| p.min(self.entries.len().saturating_sub(1)))
+            });
+
+        if self.entries.is_empty() {
+            self.state.select(None)
+        } else {
+            self.state.select(position.or(Some(0)))
+        }
+
+        self.calculate_status(outdated);
+
+        Ok(())
+    }
+
+    fn calculate_status(&self, entries: Vec<ListEntry>) {
+        let channel: Option<UnboundedSender<Action>> = self.command_tx.clone();
+        let workspace = self.workspace().clone();
+        tokio::spawn(async move {
+            for entry in entries {
+                if entry.is_directory {
+                    send_through_channel(
+                        &channel,
+                        Action::EntryStatusChanged(
+                            workspace.name.clone(),
+                            entry.relative_path,
+                            EntryStatus::Directory,
+                        ),
+                    );
+                    continue;
+                }
+                match workspace.status_of(&entry.relative_path) {
+                    core::result::Result::Ok(status) => send_through_channel(
+                        &channel,
+                        Action::EntryStatusChanged(
+                            workspace.name.clone(),
+                            entry.relative_path,
+                            status,
+                        ),
+                    ),
+                    Err(e) => {
+                        log::error!("Error reading file {} : {}", entry.relative_path, e);
+                    }
+                }
+            }
+        });
     }
 
     pub fn select_all_in_directory(&mut self, state: &mut AppState) {
         let entries = self
-            .repository
+            .repository()
             .read_files_in_directory()
             .unwrap_or_default();
 
-        state.add_many(&entries);
+        state.add_many(&self.workspace().name, &entries);
+    }
+
+    /// Selects scripts added or modified in git since the base ref of the repository.
+    pub fn select_changed_in_git(&mut self, state: &mut AppState) {
+        let repository = self.repository();
+        match repository.changed_since(repository.base_ref(), None) {
+            core::result::Result::Ok(changed) => state.add_many(&self.workspace().name, &changed),
+            Err(e) => log::error!(
+                "Cannot list changes since {}: {:?}",
+                repository.base_ref(),
+                e
+            ),
+        }
+    }
+
+    /// Queues repeatable scripts whose content differs from their last successful run.
+    pub fn select_changed_repeatables(&mut self, state: &mut AppState) {
+        let workspace = self.workspace();
+        let changed: Vec<String> = self
+            .repository()
+            .read_repeatable_scripts()
+            .into_iter()
+            .filter(|script| !workspace.is_applied(script))
+            .collect();
+
+        state.add_many(&workspace.name, &changed);
+    }
+
+    /// Asks for a description on the plain terminal, creates the next script in the
+    /// current directory and opens it in the editor. The app leaves the terminal
+    /// to the list while this runs.
+    fn new_script(&self) -> Option<Action> {
+        let directory = self.repository().current_relative_as_str();
+        let directory = directory.trim_start_matches(std::path::MAIN_SEPARATOR);
+
+        let description: String = cliclack::input("Description of the new script")
+            .interact()
+            .ok()?;
+        let ticket: String = cliclack::input("Ticket").required(false).interact().ok()?;
+
+        let created = scaffold::create(
+            self.repository(),
+            directory,
+            &description,
+            &ticket,
+            self.config.scaffold.as_ref(),
+        )
+        .map_err(|e| e.to_string())
+        .and_then(|(relative, path)| {
+            scaffold::open_in_editor(&path).map_err(|e| e.to_string())?;
+            std::result::Result::Ok(relative)
+        });
+
+        match created {
+            std::result::Result::Ok(relative) => Some(Action::RepositoryChanged(
+                self.workspace().name.clone(),
+                vec![relative],
+            )),
+            Err(e) => {
+                log::error!("Failed to create script: {}", e);
+                let _ = cliclack::outro_cancel(format!("{} (press Enter to go back)", e));
+                let _ = std::io::stdin().read_line(&mut String::new());
+                None
+            }
+        }
     }
 }
 
@@ -211,6 +609,10 @@ impl Component for List {
     fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
         tx.send(Action::CalculateEntryStatus)?;
         self.command_tx = Some(tx);
+        self.watch();
+        for index in 0..self.repositories.len() {
+            self.load_dependencies(index);
+        }
         Ok(())
     }
 
@@ -246,8 +648,13 @@ impl Component for List {
                 self.leave_current_directory()?;
                 return Ok(None);
             }
+            Action::SwitchRepository => {
+                self.switch_repository()?;
+                return Ok(None);
+            }
             Action::SelectCurrent => {
                 self.select_current(state);
+                self.pull_dependencies(state);
                 return Ok(None);
             }
             Action::UnselectCurrent => {
@@ -260,65 +667,80 @@ impl Component for List {
             }
             Action::SelectAllAfter => {
                 self.select_all_after(state);
+                self.pull_dependencies(state);
                 return Ok(None);
             }
             Action::SelectAllAfterInDirectory => {
                 self.select_all_after_in_directory(state);
+                self.pull_dependencies(state);
+            }
+            Action::SelectChangedInGit => {
+                self.select_changed_in_git(state);
+                self.pull_dependencies(state);
+                return Ok(None);
+            }
+            Action::SelectChangedRepeatables => {
+                self.select_changed_repeatables(state);
+                self.pull_dependencies(state);
+                return Ok(None);
             }
             Action::SelectAllInDirectory => {
                 self.select_all_in_directory(state);
+                self.pull_dependencies(state);
+                return Ok(None);
+            }
+            Action::MarkSelected(applied) => {
+                self.mark_selected(state, applied);
                 return Ok(None);
             }
+            Action::MarkUntilCurrent => {
+                self.mark_until_current(state);
+                return Ok(None);
+            }
+            Action::RekeyCurrent => {
+                self.rekey_current();
+                return Ok(None);
+            }
+            Action::NextMoveCandidate => {
+                self.next_move_candidate();
+                return Ok(None);
+            }
+            Action::RekeyAll => {
+                self.rekey_all();
+                return Ok(None);
+            }
+            Action::NewScript => return Ok(self.new_script()),
             Action::CalculateEntryStatus => {
-                let channel: Option<UnboundedSender<Action>> = self.command_tx.clone();
-                let memory = self.script_memory.clone();
-                let base = self.base.clone();
-                let entries: Vec<_> = self.entries.clone();
-                tokio::spawn(async move {
-                    for entry in entries {
-                        if entry.is_directory {
-                            send_through_channel(
-                                &channel,
-                                Action::EntryStatusChanged(
-                                    entry.relative_path,
-                                    EntryStatus::Directory,
-                                ),
-                            );
-                            continue;
-                        }
-                        let full_path = base.join(&entry.relative_path);
-
-                        let content = tokio::fs::read_to_string(full_path).await;
-                        match content {
-                            core::result::Result::Ok(content) => {
-                                let hasher = Crc::<u32>::new(&CRC_32_ISO_HDLC);
-                                let crc = hasher.checksum(content.as_bytes());
-                                let status = memory.get_file_status(&entry.relative_path, &crc);
-
-                                if let core::result::Result::Ok(status) = status {
-                                    send_through_channel(
-                                        &channel,
-                                        Action::EntryStatusChanged(entry.relative_path, status),
-                                    )
-                                }
-                            }
-                            Err(e) => {
-                                log::error!("Error reading file {} : {}", e, entry.relative_path);
-                            }
-                        }
-                    }
-                });
-
+                self.share_dependencies(state);
+                self.calculate_status(self.entries.clone());
                 return Ok(None);
             }
-            Action::EntryStatusChanged(path, status) => {
-                let index = self
-                    .entries
-                    .iter()
-                    .position(|e| e.relative_path == path)
-                    .unwrap();
-
-                self.entries[index].status = status.clone();
+            Action::RepositoryChanged(name, changed) => {
+                let Some(index) = self.workspaces.iter().position(|w| w.name == name) else {
+                    return Ok(None);
+                };
+                let changed = self.repositories[index].update_index(&changed);
+                if changed.is_empty() {
+                    return Ok(None);
+                }
+                if index == self.current {
+                    self.refresh(&changed)?;
+                }
+                self.load_dependencies(index);
+                return Ok(None);
+            }
+            Action::DependenciesLoaded(name, graph) => {
+                self.dependencies_loaded(&name, graph);
+                self.share_dependencies(state);
+                return Ok(None);
+            }
+            Action::EntryStatusChanged(repository, path, status) => {
+                if repository != self.workspace().name {
+                    return Ok(None);
+                }
+                if let Some(entry) = self.entries.iter_mut().find(|e| e.relative_path == path) {
+                    entry.status = status;
+                }
 
                 return Ok(None);
             }
@@ -334,13 +756,37 @@ impl Component for List {
             .split(area);
 
         let path_span = Span::raw(
-            self.repository
+            self.repository()
                 .current_as_path_buf()
                 .as_path()
                 .display()
                 .to_string(),
         );
-        let path_draw = Line::default().spans(vec![path_span]);
+        let mut spans = vec![path_span];
+        if self.workspaces.len() > 1 {
+            let name_span = Span::styled(
+                format!("[{}] ", self.workspace().name),
+                Style::new().yellow(),
+            );
+            spans.insert(0, name_span);
+        }
+        if let Some(ref profile) = self.config.profile {
+            spans.insert(
+                0,
+                Span::styled(format!("<{}> ", profile), Style::new().black().on_magenta()),
+            );
+        }
+        if let Some(problem) = self.problems.first() {
+            let more = match self.problems.len() {
+                1 => String::new(),
+                count => format!(" (+{} more)", count - 1),
+            };
+            spans.push(Span::styled(
+                format!("  \u{026A0} {}{}", problem, more),
+                Style::new().red(),
+            ));
+        }
+        let path_draw = Line::default().spans(spans);
 
         let items: Vec<ListItem> = self
             .entries
@@ -350,6 +796,8 @@ impl Component for List {
                 let decoratation = match entry.status {
                     EntryStatus::Finished(true) => ("\u{02705}", Style::new().fg(Color::Green)),
                     EntryStatus::Finished(false) => ("\u{0274E}", Style::new().fg(Color::Red)),
+                    EntryStatus::Marked => ("\u{02611}", Style::new().fg(Color::Green)),
+                    EntryStatus::Moved(_) => ("\u{1F500}", Style::new().fg(Color::Cyan)),
                     EntryStatus::Changed => ("\u{02755}", Style::new().fg(Color::Rgb(255, 165, 0))),
                     EntryStatus::Unknown => ("\u{02754}", Style::default()),
                     EntryStatus::NeverStarted => {
@@ -357,10 +805,7 @@ impl Component for List {
                     }
                     EntryStatus::Directory => ("", Style::default().bg(Color::LightBlue)),
                 };
-                let selected = state
-                    .selected
-                    .iter()
-                    .any(|s| s.relative_path == entry.relative_path);
+                let selected = state.contains(&self.workspace().name, &entry.relative_path);
 
                 let style = match (selected, entry.is_directory) {
                     (_, true) => Style::new().light_blue(),
@@ -370,17 +815,92 @@ impl Component for List {
 
                 let symbol = Span::styled(decoratation.0, decoratation.1);
 
-                let line = Line::default().spans(vec![
+                let git = match entry.git {
+                    Some(GitStatus::Added) => Span::styled("A ", Style::new().green()),
+                    Some(GitStatus::Modified) => Span::styled("M ", Style::new().yellow()),
+                    Some(GitStatus::Untracked) => Span::styled("? ", Style::new().dark_gray()),
+                    None => Span::raw("  "),
+                };
+
+                let mut spans = vec![
                     symbol,
                     Span::styled(" ", style),
+                    git,
                     Span::styled(name, style),
-                ]);
+                ];
+                if let Some(encoding) = entry.encoding.filter(|e| *e != encoding_rs::UTF_8.name()) {
+                    spans.push(Span::styled(
+                        format!(" [{}]", encoding),
+                        Style::new().dark_gray(),
+                    ));
+                }
+                if entry.rollback {
+                    let applied = matches!(
+                        entry.status,
+                        EntryStatus::Finished(true) | EntryStatus::Marked
+                    );
+                    let color = if applied {
+                        Color::Green
+                    } else {
+                        Color::DarkGray
+                    };
+                    spans.push(Span::styled(" \u{021A9}", Style::new().fg(color)));
+                }
+                if entry.repeatable {
+                    spans.push(Span::styled(" \u{021BB}", Style::new().fg(Color::Magenta)));
+                }
+                if let Some(worst) = entry.lint.iter().map(|f| f.severity).max() {
+                    let color = match worst {
+                        Severity::Error => Color::Red,
+                        _ => Color::Yellow,
+                    };
+                    spans.push(Span::styled(" \u{026A0}", Style::new().fg(color)));
+                }
+                let line = Line::default().spans(spans);
 
                 let list_item = ListItem::new(line).style(style);
                 list_item
             })
             .collect();
 
+        // Candidates of an ambiguous move of the highlighted script, otherwise its findings,
+        // the first one with a count of the others
+        let highlighted = self.state.selected().and_then(|i| self.entries.get(i));
+        let findings = highlighted
+            .map(|entry| entry.lint.as_slice())
+            .unwrap_or_default();
+        let info_title = match (highlighted.map(|entry| &entry.status), findings.first()) {
+            (Some(EntryStatus::Moved(candidates)), _) if candidates.len() > 1 => {
+                let index = highlighted
+                    .map(|entry| self.move_candidate_of(&entry.relative_path))
+                    .unwrap_or_default()
+                    % candidates.len();
+                Line::styled(
+                    format!(
+                        " Moved from {} ({}/{}), l: next candidate, k: carry over ",
+                        candidates[index],
+                        index + 1,
+                        candidates.len()
+                    ),
+                    Style::new().fg(Color::Cyan),
+                )
+                .left_aligned()
+            }
+            (_, Some(finding)) => {
+                let more = match findings.len() {
+                    1 => String::new(),
+                    count => format!(" (+{} more)", count - 1),
+                };
+                let color = match finding.severity {
+                    Severity::Error => Color::Red,
+                    _ => Color::Yellow,
+                };
+                Line::styled(format!(" {}{} ", finding, more), Style::new().fg(color))
+                    .left_aligned()
+            }
+            _ => Line::default(),
+        };
+
         let list_draw = ratatui::widgets::List::new(items)
             .block(
                 Block::default()
@@ -388,7 +908,8 @@ impl Component for List {
                     .border_type(BorderType::Double)
                     .title_position(Position::Bottom)
                     .title_alignment(Alignment::Right)
-                    .title("Press h for help"),
+                    .title("Press h for help")
+                    .title(info_title),
             )
             .highlight_style(Style::default().add_modifier(Modifier::BOLD))
             .highlight_symbol(">> ")
@@ -399,3 +920,121 @@ impl Component for List {
         Ok(())
     }
 }
+
+/// Marks scripts of the workspace as applied, or resets them when `applied` is false.
+fn mark(workspace: &Workspace, scripts: &[String], applied: bool) {
+    for script in scripts {
+        let result = if applied {
+            workspace
+                .source
+                .read(script)
+                .map_err(eyre::Report::from)
+                .and_then(|content| {
+                    workspace
+                        .script_memory
+                        .mark_applied(script.clone(), checksum(&content))
+                })
+        } else {
+            workspace.script_memory.reset(script)
+        };
+
+        if let Err(e) = result {
+            log::error!("Failed to mark script {} : {}", script, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use super::*;
+    use crate::{
+        db::{Authentication, Database},
+        script_memory::ScriptDatabase,
+    };
+
+    #[tokio::test]
+    async fn refresh_keeps_cursor_and_status() {
+        let root = std::env::temp_dir().join(format!("squealmate-refresh-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("scripts")).unwrap();
+        fs::write(root.join("scripts").join("001.sql"), "SELECT 1").unwrap();
+        fs::write(root.join("scripts").join("002.sql"), "SELECT 2").unwrap();
+
+        let repository = Repository::new(root.join("scripts")).unwrap();
+        let workspace = workspace("default", &repository, &root);
+        let mut list = List::new(vec![repository], vec![workspace], 0).unwrap();
+        list.state.select(Some(1));
+        list.entries[0].status = EntryStatus::Finished(true);
+        list.entries[1].status = EntryStatus::Finished(true);
+
+        fs::write(root.join("scripts").join("000.sql"), "SELECT 0").unwrap();
+        fs::write(root.join("scripts").join("002.sql"), "SELECT 22").unwrap();
+        list.refresh(&["000.sql".into(), "002.sql".into()]).unwrap();
+
+        let names: Vec<_> = list.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(vec!["000.sql", "001.sql", "002.sql"], names);
+        assert_eq!(
+            Some("002.sql"),
+            list.get_selection().map(|e| e.name.as_str())
+        );
+
+        // Only the entries that changed have their status calculated again
+        assert_eq!(EntryStatus::Finished(true), list.entries[1].status);
+        assert_ne!(EntryStatus::Finished(true), list.entries[2].status);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn index_other_repositories() {
+        let root = std::env::temp_dir().join(format!("squealmate-others-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for name in ["first", "second"] {
+            fs::create_dir_all(root.join(name)).unwrap();
+            fs::write(root.join(name).join("001.sql"), "SELECT 1").unwrap();
+        }
+
+        let repositories: Vec<_> = ["first", "second"]
+            .iter()
+            .map(|name| Repository::new(root.join(name)).unwrap())
+            .collect();
+        for repository in repositories.iter() {
+            repository.build_index().join().unwrap();
+        }
+        let workspaces = vec![
+            workspace("first", &repositories[0], &root.join("first")),
+            workspace("second", &repositories[1], &root.join("second")),
+        ];
+        let mut list = List::new(repositories, workspaces, 0).unwrap();
+
+        // Changes to the repository not shown reach its index, not the entries shown
+        fs::write(root.join("second").join("002.sql"), "SELECT 2").unwrap();
+        let changed = Action::RepositoryChanged("second".into(), vec!["002.sql".into()]);
+        list.update(&mut AppState::new(vec![]), changed).unwrap();
+        assert_eq!(1, list.entries.len());
+
+        list.switch_repository().unwrap();
+        let names: Vec<_> = list.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(vec!["001.sql", "002.sql"], names);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    fn workspace(name: &str, repository: &Repository, root: &Path) -> Workspace {
+        Workspace {
+            name: name.into(),
+            source: repository.source().clone(),
+            connection: Database {
+                server: "localhost".into(),
+                port: 1433,
+                name: "test".into(),
+                authentication: Authentication::Integrated,
+            },
+            script_memory: ScriptDatabase::open(root.join("scripts.db")).unwrap(),
+            encoding: repository.encoding(),
+            rules: repository.rules().clone(),
+        }
+    }
+}
@@ -9,8 +9,10 @@ use crossterm::event::{KeyEvent, MouseEvent};
 use ratatui::layout::{Rect, Size};
 use tokio::sync::mpsc::UnboundedSender;
 
+pub mod confirm;
 pub mod help;
 pub mod list;
+pub mod orphans;
 pub mod script_status;
 pub mod scroll_list;
 
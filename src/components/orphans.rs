@@ -0,0 +1,169 @@
+use color_eyre::eyre::Result;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, BorderType, Borders, List, ListItem, ListState},
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::Component;
+use crate::{
+    action::Action, app::AppState, config::Settings, repository::Repository, screen::Mode,
+    tui::Frame, workspace::Workspace,
+};
+
+pub struct Orphans {
+    command_tx: Option<UnboundedSender<Action>>,
+    config: Settings,
+    state: ListState,
+    /// Repositories of the session, in the same order as `workspaces`
+    repositories: Vec<Repository>,
+    workspaces: Vec<Workspace>,
+    /// Orphaned records as indexes into `workspaces` and script names
+    orphans: Vec<(usize, String)>,
+}
+
+impl Orphans {
+    pub fn new(repositories: Vec<Repository>, workspaces: Vec<Workspace>) -> Self {
+        Self {
+            command_tx: None,
+            config: Settings::default(),
+            state: ListState::default().with_selected(Some(0)),
+            repositories,
+            workspaces,
+            orphans: vec![],
+        }
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        self.orphans.clear();
+        for (index, (workspace, repository)) in self
+            .workspaces
+            .iter()
+            .zip(self.repositories.iter())
+            .enumerate()
+        {
+            let scripts = repository.get_children("".into());
+            let orphans = workspace
+                .script_memory
+                .find_orphans(&workspace.source, &scripts)?;
+            self.orphans
+                .extend(orphans.into_iter().map(|name| (index, name)));
+        }
+
+        if self.orphans.is_empty() {
+            self.state.select(None)
+        } else {
+            self.state.select(Some(0))
+        }
+
+        Ok(())
+    }
+
+    fn orphans_of(&self, workspace: usize) -> Vec<String> {
+        self.orphans
+            .iter()
+            .filter(|(index, _)| *index == workspace)
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
+
+    pub fn cursor_up(&mut self) {
+        if let Some(position) = self.state.selected() {
+            if position > 0 {
+                self.state.select(Some(position - 1))
+            }
+        }
+    }
+
+    pub fn cursor_down(&mut self) {
+        if let Some(position) = self.state.selected() {
+            if position + 1 < self.orphans.len() {
+                self.state.select(Some(position + 1))
+            }
+        }
+    }
+}
+
+impl Component for Orphans {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Settings) -> Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, _: &mut AppState, action: Action) -> Result<Option<Action>> {
+        match action {
+            Action::SwitchMode(Mode::Orphans) => self.reload()?,
+            Action::CursorUp => self.cursor_up(),
+            Action::CursorDown => self.cursor_down(),
+            Action::CursorToTop if !self.orphans.is_empty() => self.state.select(Some(0)),
+            Action::CursorToBottom if !self.orphans.is_empty() => {
+                self.state.select(Some(self.orphans.len() - 1))
+            }
+            Action::RequestPruneOrphans if !self.orphans.is_empty() => {
+                return Ok(Some(Action::Confirm(
+                    format!(
+                        "Delete {} history records for good?\n\
+                         Cancel and press a to archive them instead, which keeps them for reference.",
+                        self.orphans.len()
+                    ),
+                    Box::new(Action::PruneOrphans),
+                )));
+            }
+            Action::PruneOrphans => {
+                for (index, workspace) in self.workspaces.iter().enumerate() {
+                    workspace.script_memory.prune(&self.orphans_of(index))?;
+                }
+                self.reload()?;
+                return Ok(Some(Action::CalculateEntryStatus));
+            }
+            Action::ArchiveOrphans => {
+                for (index, workspace) in self.workspaces.iter().enumerate() {
+                    workspace.script_memory.archive(&self.orphans_of(index))?;
+                }
+                self.reload()?;
+                return Ok(Some(Action::CalculateEntryStatus));
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect, _: &AppState) -> Result<()> {
+        let items: Vec<ListItem> = self
+            .orphans
+            .iter()
+            .map(|(index, name)| {
+                let text = if self.workspaces.len() > 1 {
+                    format!("[{}] {}", self.workspaces[*index].name, name)
+                } else {
+                    name.clone()
+                };
+                ListItem::new(Span::styled(text, Style::new().fg(Color::Gray)))
+            })
+            .collect();
+
+        let list_draw = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Double)
+                    .title(format!(
+                        "Orphaned history ({}) - p: prune, a: archive",
+                        self.orphans.len()
+                    ))
+                    .title_bottom(Line::from("Press h for help").alignment(Alignment::Right)),
+            )
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">> ")
+            .repeat_highlight_symbol(true);
+
+        f.render_stateful_widget(&list_draw, area, &mut self.state);
+
+        Ok(())
+    }
+}
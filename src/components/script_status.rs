@@ -64,6 +64,11 @@ impl Component for ScriptStatus {
                         elapsed: Some(elapsed),
                         ..
                     }) => format!("Finished in: {}ms", elapsed),
+                    Some(Script {
+                        state: ScriptState::RolledBack,
+                        elapsed: Some(elapsed),
+                        ..
+                    }) => format!("Rolled back in: {}ms", elapsed),
                     None => String::from(""),
                     _ => String::from(""),
                 };
@@ -1,65 +1,87 @@
 use std::path::PathBuf;
 
 use color_eyre::eyre::Result;
-use crc::{Crc, CRC_32_ISO_HDLC};
 use ratatui::{
     prelude::*,
     widgets::{Block, BorderType, Borders, List, ListItem, ListState},
 };
-use tokio::{sync::mpsc::UnboundedSender, time::Instant};
+use tokio::sync::mpsc::UnboundedSender;
 
 use super::Component;
 use crate::{
     action::Action,
     app::{AppState, Script, ScriptState},
     config::Settings,
-    db::Database,
-    script_memory::ScriptDatabase,
+    entries::EntryStatus,
+    manifest::{Manifest, ManifestEntry},
+    report::Report,
+    runner,
     tui::Frame,
     utils::send_through_channel,
+    workspace::{self, Workspace},
 };
 
+/// Scripts listed when asking before a rollback, the rest are only counted.
+const MAX_LISTED_ROLLBACKS: usize = 10;
+
 pub struct ScrollList {
     command_tx: Option<UnboundedSender<Action>>,
     config: Settings,
     state: ListState,
-    db: Database,
-    base: PathBuf,
-    script_memory: ScriptDatabase,
+    workspaces: Vec<Workspace>,
+    /// File the queue is saved to as a manifest
+    manifest: PathBuf,
+    /// Outcome of the last manifest save or a report that could not be written, shown in the title
+    notice: Option<String>,
+    /// Rewritten after every script, so it covers the run so far
+    report: Option<Report>,
 }
 
 impl ScrollList {
-    pub fn new(db: Database, base: PathBuf, script_memory: ScriptDatabase) -> Self {
+    pub fn new(workspaces: Vec<Workspace>, manifest: PathBuf, report: Option<Report>) -> Self {
         Self {
             command_tx: None,
             config: Settings::default(),
             state: ListState::default().with_selected(Some(0)),
-            db,
-            base,
-            script_memory,
+            workspaces,
+            manifest,
+            notice: None,
+            report,
         }
     }
 
-    fn update_selection(&mut self, state: &mut AppState) {
-        if self.command_tx.is_none() {
+    /// Writes the results of the queue to the report, if there is one.
+    pub fn write_report(&mut self, state: &AppState) {
+        let Some(ref report) = self.report else {
             return;
+        };
+
+        if let Err(e) = report.write(&state.selected) {
+            log::error!("Cannot write report {}: {}", report.path.display(), e);
+            self.notice = Some(format!("Cannot write report: {}", e));
         }
+    }
 
-        if let Some(channel) = self.command_tx.as_mut() {
-            let selected: Vec<String> = state
+    /// Writes the queue out as a manifest, naming repositories only when there are several.
+    pub fn save_manifest(&mut self, state: &AppState) {
+        let manifest = Manifest {
+            scripts: state
                 .selected
                 .iter()
-                .map(|e| e.relative_path.clone())
-                .collect();
-
-            if selected.is_empty() {
-                return;
-            }
+                .map(|script| ManifestEntry {
+                    repository: (self.workspaces.len() > 1).then(|| script.repository.clone()),
+                    path: script.relative_path.clone(),
+                })
+                .collect(),
+        };
 
-            if let Err(error) = channel.send(Action::SelectionChanged(selected)) {
-                log::error!("{}", error);
+        self.notice = Some(match manifest.save(&self.manifest) {
+            Ok(()) => format!("Manifest saved to {}", self.manifest.display()),
+            Err(e) => {
+                log::error!("{}", e);
+                e.to_string()
             }
-        }
+        });
     }
 
     fn get_update(&self, state: &mut AppState) -> Result<Option<Action>> {
@@ -118,11 +140,118 @@ impl ScrollList {
 
         let entry = entry.unwrap();
 
-        state.remove(entry.relative_path);
+        state.remove(&entry.repository, entry.relative_path);
     }
 
     pub fn unselect_all(&mut self, state: &mut AppState) {
-        state.selected.clear()
+        state.clear()
+    }
+}
+
+/// What running a script from the queue does.
+#[derive(Clone, Copy)]
+enum Run {
+    /// Applies the script, continuing with the next one after an error when `skip_errors` is set
+    Apply { skip_errors: bool },
+    /// Executes the rollback of the script, stopping at the first error
+    Rollback,
+}
+
+impl ScrollList {
+    /// Scripts of the queue a rollback undoes, in the order it undoes them, along with
+    /// their rollback scripts. Goes from the end of the queue, skipping scripts that were
+    /// not applied or cannot be undone.
+    fn rollbacks(&self, state: &AppState) -> Vec<(Script, &Workspace, String)> {
+        state
+            .selected
+            .iter()
+            .rev()
+            .filter(|script| script.state == ScriptState::None)
+            .filter_map(|script| {
+                let workspace = workspace::find(&self.workspaces, &script.repository)?;
+                if !workspace.is_applied(&script.relative_path) {
+                    return None;
+                }
+                let rollback = workspace.rollback_of(&script.relative_path)?;
+                Some((script.clone(), workspace, rollback))
+            })
+            .collect()
+    }
+
+    /// Executes the script at `file` on behalf of `entry` in the background, reporting progress
+    /// through actions and continuing with the next script of the queue.
+    fn spawn_script(&self, workspace: &Workspace, entry: Script, file: String, run: Run) {
+        let workspace = workspace.clone();
+        let channel: Option<UnboundedSender<Action>> = self.command_tx.clone();
+
+        tokio::spawn(async move {
+            send_through_channel(&channel, Action::ScriptRunning(entry.clone()));
+
+            let execution = runner::execute(&workspace, &file).await;
+            let elapsed = execution.elapsed;
+            let Some(crc) = execution.crc else {
+                if let Err(err) = execution.result {
+                    send_through_channel(&channel, Action::ScriptError(entry, err, None));
+                }
+                return;
+            };
+
+            match (execution.result, run) {
+                (Ok(output), Run::Apply { skip_errors }) => {
+                    let entry = Script { output, ..entry };
+                    send_through_channel(
+                        &channel,
+                        Action::ScriptFinished(entry.clone(), elapsed, crc),
+                    );
+                    send_through_channel(
+                        &channel,
+                        Action::EntryStatusChanged(
+                            entry.repository,
+                            entry.relative_path,
+                            EntryStatus::Finished(true),
+                        ),
+                    );
+                    send_through_channel(&channel, Action::ScriptRun(skip_errors));
+                }
+                (Ok(output), Run::Rollback) => {
+                    let entry = Script { output, ..entry };
+                    send_through_channel(
+                        &channel,
+                        Action::ScriptRolledBack(entry.clone(), elapsed),
+                    );
+                    send_through_channel(
+                        &channel,
+                        Action::EntryStatusChanged(
+                            entry.repository,
+                            entry.relative_path,
+                            EntryStatus::NeverStarted,
+                        ),
+                    );
+                    send_through_channel(&channel, Action::RollbackRun);
+                }
+                (Err(err), Run::Apply { skip_errors }) => {
+                    send_through_channel(
+                        &channel,
+                        Action::ScriptError(entry.clone(), err, Some(crc)),
+                    );
+                    send_through_channel(
+                        &channel,
+                        Action::EntryStatusChanged(
+                            entry.repository,
+                            entry.relative_path,
+                            EntryStatus::Finished(false),
+                        ),
+                    );
+                    if skip_errors {
+                        send_through_channel(&channel, Action::ScriptRun(skip_errors));
+                    }
+                }
+                (Err(err), Run::Rollback) => {
+                    // The script stays applied, so its history is left as it is
+                    send_through_channel(&channel, Action::ScriptError(entry, err, None));
+                }
+            }
+        });
     }
 }
 
@@ -160,7 +289,7 @@ impl Component for ScrollList {
                 let new_position = state
                     .selected
                     .iter_mut()
-                    .position(|s| s.relative_path == entry);
+                    .position(|s| s.is(&entry.repository, &entry.relative_path));
 
                 if let Some(new_position) = new_position {
                     self.go_to_entry(new_position);
@@ -169,21 +298,27 @@ impl Component for ScrollList {
                 state
                     .selected
                     .iter_mut()
-                    .filter(|s| s.relative_path == entry)
+                    .filter(|s| s.is(&entry.repository, &entry.relative_path))
                     .for_each(|s| {
                         s.state = ScriptState::Finished;
                         s.elapsed = Some(elapsed);
+                        s.output = entry.output.clone();
                     });
 
-                self.script_memory.insert(entry, crc, true)?;
+                if let Some(workspace) = workspace::find(&self.workspaces, &entry.repository) {
+                    workspace
+                        .script_memory
+                        .insert(entry.relative_path, crc, true)?;
+                }
 
+                self.write_report(state);
                 return self.get_update(state);
             }
             Action::ScriptError(entry, message, crc) => {
                 let new_position = state
                     .selected
                     .iter_mut()
-                    .position(|s| s.relative_path == entry);
+                    .position(|s| s.is(&entry.repository, &entry.relative_path));
 
                 if let Some(new_position) = new_position {
                     self.go_to_entry(new_position);
@@ -192,22 +327,26 @@ impl Component for ScrollList {
                 state
                     .selected
                     .iter_mut()
-                    .filter(|s| s.relative_path == entry)
+                    .filter(|s| s.is(&entry.repository, &entry.relative_path))
                     .for_each(|s| {
                         s.state = ScriptState::Error;
                         s.error = Some(message.clone())
                     });
 
-                if let Some(crc) = crc {
-                    self.script_memory.insert(entry, crc, false)?;
+                let workspace = workspace::find(&self.workspaces, &entry.repository);
+                if let (Some(crc), Some(workspace)) = (crc, workspace) {
+                    workspace
+                        .script_memory
+                        .insert(entry.relative_path, crc, false)?;
                 }
 
+                self.write_report(state);
                 return self.get_update(state);
             }
             Action::ScriptRunning(entry) => state
                 .selected
                 .iter_mut()
-                .filter(|s| s.relative_path == entry)
+                .filter(|s| s.is(&entry.repository, &entry.relative_path))
                 .for_each(|s| s.state = ScriptState::Running),
             Action::UnselectCurrent => {
                 self.unselect_current(state);
@@ -217,114 +356,94 @@ impl Component for ScrollList {
                 self.unselect_all(state);
                 return Ok(None);
             }
-            Action::AddSelection(scripts) => {
-                let mut only_new: Vec<Script> = scripts
+            Action::SaveManifest => {
+                self.save_manifest(state);
+                return Ok(None);
+            }
+            Action::ScriptRun(skip_errors) => {
+                let Some(entry) = state
+                    .selected
+                    .iter()
+                    .find(|f| f.state == ScriptState::None)
+                    .cloned()
+                else {
+                    return Ok(None);
+                };
+
+                let Some(workspace) = workspace::find(&self.workspaces, &entry.repository) else {
+                    log::error!("Unknown repository {}", entry.repository);
+                    return Ok(None);
+                };
+
+                let file = entry.relative_path.clone();
+                self.spawn_script(workspace, entry, file, Run::Apply { skip_errors });
+
+                return self.get_update(state);
+            }
+            Action::RequestRollback => {
+                let scripts: Vec<String> = self
+                    .rollbacks(state)
                     .into_iter()
-                    .filter(|s| !state.selected.iter().any(|r| r.relative_path == *s))
-                    .map(|s| Script::none(&s))
+                    .map(|(script, _, _)| match self.workspaces.len() {
+                        1 => script.relative_path,
+                        _ => format!("[{}] {}", script.repository, script.relative_path),
+                    })
                     .collect();
-                state.selected.append(&mut only_new);
-                state.selected.sort();
+                if scripts.is_empty() {
+                    return Ok(None);
+                }
 
-                self.update_selection(state);
+                let mut message = format!("Roll back {} scripts, in this order?\n", scripts.len());
+                for script in scripts.iter().take(MAX_LISTED_ROLLBACKS) {
+                    message.push_str(&format!("\n{}", script));
+                }
+                if scripts.len() > MAX_LISTED_ROLLBACKS {
+                    message.push_str(&format!(
+                        "\n... and {} more",
+                        scripts.len() - MAX_LISTED_ROLLBACKS
+                    ));
+                }
 
-                return self.get_update(state);
+                return Ok(Some(Action::Confirm(
+                    message,
+                    Box::new(Action::RollbackRun),
+                )));
             }
-            Action::RemoveSelection(scripts) => {
-                state
-                    .selected
-                    .retain(|e| !scripts.contains(&e.relative_path));
+            Action::RollbackRun => {
+                let Some((entry, workspace, rollback)) = self.rollbacks(state).into_iter().next()
+                else {
+                    return Ok(None);
+                };
 
-                self.update_selection(state);
+                self.spawn_script(workspace, entry, rollback, Run::Rollback);
 
                 return self.get_update(state);
             }
-            Action::ScriptRun(skip_errors) => {
-                let first_not_run_entry = state
+            Action::ScriptRolledBack(entry, elapsed) => {
+                let new_position = state
                     .selected
-                    .iter()
-                    .find(|f| f.state == ScriptState::None)
-                    .cloned();
+                    .iter_mut()
+                    .position(|s| s.is(&entry.repository, &entry.relative_path));
 
-                if first_not_run_entry.is_none() {
-                    return Ok(None);
+                if let Some(new_position) = new_position {
+                    self.go_to_entry(new_position);
                 }
-                let entry = first_not_run_entry.unwrap();
-
-                let full_path = self.base.join(&entry.relative_path);
-
-                let connection = self.db.clone();
-                let channel: Option<UnboundedSender<Action>> = self.command_tx.clone();
-                let cloned = entry.clone();
 
-                tokio::spawn(async move {
-                    send_through_channel(
-                        &channel,
-                        Action::ScriptRunning(cloned.relative_path.clone()),
-                    );
+                state
+                    .selected
+                    .iter_mut()
+                    .filter(|s| s.is(&entry.repository, &entry.relative_path))
+                    .for_each(|s| {
+                        s.state = ScriptState::RolledBack;
+                        s.elapsed = Some(elapsed);
+                        s.output = entry.output.clone();
+                    });
 
-                    let now = Instant::now();
-                    let content = tokio::fs::read_to_string(full_path).await;
-                    match content {
-                        Ok(content) => {
-                            let result = connection.execute_script(&content).await;
-                            let elapsed = now.elapsed().as_millis();
-                            let hasher = Crc::<u32>::new(&CRC_32_ISO_HDLC);
-                            let crc = hasher.checksum(content.as_bytes());
-                            match result {
-                                Ok(_) => {
-                                    send_through_channel(
-                                        &channel,
-                                        Action::ScriptFinished(
-                                            cloned.relative_path.clone(),
-                                            elapsed,
-                                            crc,
-                                        ),
-                                    );
-                                    send_through_channel(
-                                        &channel,
-                                        Action::EntryStatusChanged(
-                                            cloned.relative_path,
-                                            crate::entries::EntryStatus::Finished(true),
-                                        ),
-                                    );
-                                    send_through_channel(&channel, Action::ScriptRun(skip_errors));
-                                }
-                                Err(err) => {
-                                    send_through_channel(
-                                        &channel,
-                                        Action::ScriptError(
-                                            cloned.relative_path.clone(),
-                                            err.to_string(),
-                                            Some(crc),
-                                        ),
-                                    );
-                                    send_through_channel(
-                                        &channel,
-                                        Action::EntryStatusChanged(
-                                            cloned.relative_path,
-                                            crate::entries::EntryStatus::Finished(false),
-                                        ),
-                                    );
-                                    if skip_errors {
-                                        send_through_channel(
-                                            &channel,
-                                            Action::ScriptRun(skip_errors),
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            send_through_channel(
-                                &channel,
-                                Action::ScriptError(cloned.relative_path, err.to_string(), None),
-                            );
-                        }
-                    }
-                });
+                if let Some(workspace) = workspace::find(&self.workspaces, &entry.repository) {
+                    workspace.script_memory.reset(&entry.relative_path)?;
+                }
 
-                //}
+                self.write_report(state);
                 return self.get_update(state);
             }
             _ => {}
@@ -345,12 +464,17 @@ impl Component for ScrollList {
             .selected
             .iter()
             .map(|e| {
-                let text = &e.relative_path;
+                let text = if self.workspaces.len() > 1 {
+                    format!("[{}] {}", e.repository, e.relative_path)
+                } else {
+                    e.relative_path.clone()
+                };
 
                 let style = match e.state {
                     ScriptState::None => Style::new().fg(Color::White),
                     ScriptState::Running => Style::new().fg(Color::Yellow),
                     ScriptState::Finished => Style::new().fg(Color::Green),
+                    ScriptState::RolledBack => Style::new().fg(Color::Magenta),
                     ScriptState::Error => Style::new().fg(Color::Red),
                 };
 
@@ -363,7 +487,18 @@ impl Component for ScrollList {
                 Block::default()
                     .borders(Borders::ALL)
                     .border_type(BorderType::Double)
-                    .title("Selected files"),
+                    .title(match self.notice {
+                        Some(ref notice) => format!("Selected files - {}", notice),
+                        None => "Selected files".to_string(),
+                    })
+                    .title_top(match self.config.profile {
+                        Some(ref profile) => Line::styled(
+                            format!(" {} ", profile),
+                            Style::new().black().on_magenta(),
+                        )
+                        .right_aligned(),
+                        None => Line::default(),
+                    }),
             )
             .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
             .highlight_symbol(">> ")
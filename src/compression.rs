@@ -0,0 +1,74 @@
+use std::{io, io::Read, path::Path};
+
+/// Extensions of compressed scripts, following the script's own extension as in `001.sql.gz`.
+pub const COMPRESSED_EXTENSIONS: [&str; 2] = [".gz", ".zst"];
+
+fn compressed_extension(path: &str) -> Option<&'static str> {
+    let lowercase = path.to_lowercase();
+    COMPRESSED_EXTENSIONS
+        .into_iter()
+        .find(|ext| lowercase.ends_with(ext))
+}
+
+/// Splits `path` into the script path and the compression extension, if it has one.
+pub fn split_extension(path: &str) -> (&str, &str) {
+    match compressed_extension(path) {
+        Some(ext) => path.split_at(path.len() - ext.len()),
+        None => (path, ""),
+    }
+}
+
+/// Decompresses `bytes` read from `path` based on its extension, anything else is returned as it is.
+pub fn decompress(path: &Path, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+    match path.to_str().and_then(compressed_extension) {
+        Some(".gz") => {
+            let mut content = vec![];
+            flate2::read::MultiGzDecoder::new(bytes.as_slice()).read_to_end(&mut content)?;
+            Ok(content)
+        }
+        Some(".zst") => zstd::decode_all(bytes.as_slice()),
+        _ => Ok(bytes),
+    }
+}
+
+/// Reads the content of a script, decompressed when the script is compressed.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    decompress(path, std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn compressed_extensions() {
+        assert_eq!(("001.sql", ".gz"), split_extension("001.sql.gz"));
+        assert_eq!(("001.SQL", ".ZST"), split_extension("001.SQL.ZST"));
+        assert_eq!(("001.sql", ""), split_extension("001.sql"));
+    }
+
+    #[test]
+    fn decompress_scripts() {
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gzip.write_all(b"SELECT 1").unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(
+            b"SELECT 1".to_vec(),
+            decompress(Path::new("001.sql.gz"), gzip).unwrap()
+        );
+
+        let zstd = zstd::encode_all(&b"SELECT 2"[..], 0).unwrap();
+        assert_eq!(
+            b"SELECT 2".to_vec(),
+            decompress(Path::new("002.sql.zst"), zstd).unwrap()
+        );
+
+        assert_eq!(
+            b"SELECT 3".to_vec(),
+            decompress(Path::new("003.sql"), b"SELECT 3".to_vec()).unwrap()
+        );
+        assert!(decompress(Path::new("004.sql.gz"), b"SELECT 4".to_vec()).is_err());
+    }
+}
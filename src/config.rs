@@ -1,10 +1,17 @@
-use std::{fs::create_dir_all, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    env, fmt,
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+};
 
 use color_eyre::eyre;
 use config::{Config, ConfigError, Environment, File, FileFormat};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
+use crate::{lint::Severity, report::Report, repository::ScriptOrdering};
+
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 #[allow(unused)]
 pub struct Database {
@@ -14,6 +21,12 @@ pub struct Database {
     pub username: Option<String>,
     #[serde(default)]
     pub password: Option<String>,
+    /// Environment variable holding the password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_env: Option<String>,
+    /// Shell command printing the password, such as `pass show db/dev`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_command: Option<String>,
     #[serde(default)]
     pub server: Option<String>,
     #[serde(default)]
@@ -22,11 +35,110 @@ pub struct Database {
     pub name: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[allow(unused)]
 pub struct Repository {
     #[serde(default)]
     pub path: Option<String>,
+    /// Glob patterns a script has to match, all scripts are included when empty
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Glob patterns of files and directories that are skipped, on top of [`DEFAULT_EXCLUDE`]
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// File extensions recognized as scripts
+    #[serde(default = "default_extensions")]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub ordering: ScriptOrdering,
+    /// Glob patterns of scripts that are re-applied whenever their content changes
+    #[serde(default = "default_repeatable")]
+    pub repeatable: Vec<String>,
+    /// Git ref used when selecting scripts changed on the current branch
+    #[serde(default = "default_base_ref")]
+    pub base_ref: String,
+    /// Code page of scripts that are neither UTF-8 nor UTF-16
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    /// Marks rollback scripts, `001.down.sql` undoes `001.sql`
+    #[serde(default = "default_rollback_suffix")]
+    pub rollback_suffix: String,
+}
+
+impl Default for Repository {
+    fn default() -> Self {
+        Self {
+            path: None,
+            include: vec![],
+            exclude: vec![],
+            extensions: default_extensions(),
+            ordering: ScriptOrdering::default(),
+            repeatable: default_repeatable(),
+            base_ref: default_base_ref(),
+            encoding: default_encoding(),
+            rollback_suffix: default_rollback_suffix(),
+        }
+    }
+}
+
+/// Always skipped, hiding everything starting with `_` or `.` such as the `.git` directory.
+pub const DEFAULT_EXCLUDE: [&str; 2] = ["**/_*", "**/.*"];
+
+fn default_extensions() -> Vec<String> {
+    vec!["sql".to_string()]
+}
+
+fn default_repeatable() -> Vec<String> {
+    vec!["**/R__*".to_string()]
+}
+
+pub const DEFAULT_BASE_REF: &str = "main";
+
+fn default_base_ref() -> String {
+    DEFAULT_BASE_REF.to_string()
+}
+
+fn default_rollback_suffix() -> String {
+    "down".to_string()
+}
+
+fn default_encoding() -> String {
+    crate::encoding::DEFAULT_FALLBACK.to_string()
+}
+
+/// Repository with its own name, used when a session works with several of them.
+/// Database settings left out fall back to the top-level `[database]` section.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NamedRepository {
+    pub name: String,
+    #[serde(flatten)]
+    pub repository: Repository,
+    #[serde(default)]
+    pub database: Option<Database>,
+}
+
+pub const DEFAULT_REPOSITORY: &str = "default";
+
+/// Settings of scripts created with `new`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Scaffold {
+    /// File the new script is filled from, the built-in template is used when not set
+    #[serde(default)]
+    pub template: Option<PathBuf>,
+    /// Author written to the header, taken from git when not set
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+/// Environment such as DEV or TEST, overriding the top-level connection and repository.
+/// Database settings left out fall back to the top-level `[database]` section,
+/// a repository given here replaces the top-level `[repository]` as a whole.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Profile {
+    #[serde(default)]
+    pub database: Option<Database>,
+    #[serde(default)]
+    pub repository: Option<Repository>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -36,16 +148,101 @@ pub struct Settings {
     pub database: Database,
     #[serde(default)]
     pub repository: Repository,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repositories: Vec<NamedRepository>,
+    /// Report written at the end of every run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report: Option<Report>,
+    /// Severities of lint rules by name, overriding the defaults
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lint: BTreeMap<String, Severity>,
+    /// Template and author of scripts created with `new`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scaffold: Option<Scaffold>,
+    /// Profile used when none is picked with `--profile`, the active one once applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Project file the settings were layered on, if one was found
+    #[serde(skip)]
+    pub project: Option<PathBuf>,
+}
+
+impl Database {
+    /// Fills settings missing here from `fallback`.
+    /// The password sources are taken together, so that a password configured here
+    /// in any way is not shadowed by a different kind of source in `fallback`.
+    pub fn or(&self, fallback: &Database) -> Database {
+        let has_password = self.password.is_some()
+            || self.password_env.is_some()
+            || self.password_command.is_some();
+        let passwords = if has_password { self } else { fallback };
+        Database {
+            integrated: self.integrated.or(fallback.integrated),
+            username: self.username.clone().or_else(|| fallback.username.clone()),
+            password: passwords.password.clone(),
+            password_env: passwords.password_env.clone(),
+            password_command: passwords.password_command.clone(),
+            server: self.server.clone().or_else(|| fallback.server.clone()),
+            port: self.port.or(fallback.port),
+            name: self.name.clone().or_else(|| fallback.name.clone()),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum SettingError {
     NoConfigFile,
     NotAValidPath,
-    #[allow(unused)]
     InnerInitError(ConfigError),
-    #[allow(unused)]
     InnerDeserializationError(ConfigError),
+    InvalidProjectFile(PathBuf, String),
+    /// Kind of the name and the name itself, which is not usable in file names
+    InvalidName(&'static str, String),
+}
+
+impl fmt::Display for SettingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingError::NoConfigFile => write!(f, "Cannot create the configuration file"),
+            SettingError::NotAValidPath => {
+                write!(f, "Path of the configuration file is not valid UTF-8")
+            }
+            SettingError::InnerInitError(e) => write!(f, "Cannot read the configuration: {}", e),
+            SettingError::InnerDeserializationError(e) => {
+                write!(f, "Invalid configuration: {}", e)
+            }
+            SettingError::InvalidProjectFile(path, e) => {
+                write!(f, "Invalid project file {}: {}", path.display(), e)
+            }
+            SettingError::InvalidName(kind, name) => write!(
+                f,
+                "Invalid {} name {}, only letters, digits, - and _ are allowed",
+                kind, name
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProfileError {
+    UnknownProfile(String),
+    /// Profile replaces the repository while several are configured under `[[repositories]]`
+    RepositoryWithRepositories(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::UnknownProfile(name) => write!(f, "Profile {} is not configured", name),
+            ProfileError::RepositoryWithRepositories(name) => write!(
+                f,
+                "Profile {} sets a repository, which cannot be combined with [[repositories]]",
+                name
+            ),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -60,7 +257,11 @@ impl Settings {
 
         let config_path_str = config_dir.to_str().ok_or(SettingError::NotAValidPath)?;
 
-        Self::from_path(config_path_str)
+        let project = env::current_dir()
+            .ok()
+            .and_then(|current| find_project_file(&current));
+
+        Self::from_paths(project.as_deref(), config_path_str)
     }
 
     pub fn save(&self) -> Result<(), SettingSaveError> {
@@ -75,16 +276,158 @@ impl Settings {
         Ok(())
     }
 
+    #[allow(unused)]
     pub fn from_path(config_path: &str) -> Result<Self, SettingError> {
-        let s = Config::builder()
+        Self::from_paths(None, config_path)
+    }
+
+    /// Settings of the project file, overridden by the configuration file and the environment.
+    /// Where the scripts are is always taken from the project file when there is one.
+    pub fn from_paths(project: Option<&Path>, config_path: &str) -> Result<Self, SettingError> {
+        let mut builder = Config::builder();
+        let layer = project.map(Self::project_layer).transpose()?;
+        if let (Some(project), Some(ref layer)) = (project, &layer) {
+            let content = toml::to_string(layer).map_err(|e| {
+                SettingError::InvalidProjectFile(project.to_path_buf(), e.to_string())
+            })?;
+            builder = builder.add_source(File::from_str(&content, FileFormat::Toml));
+        }
+
+        let s = builder
             // Start off by merging in the "default" configuration file
             .add_source(File::new(config_path, FileFormat::Toml).required(false))
             .add_source(Environment::with_prefix("SQUEALMATE").separator("_"))
             .build()
             .map_err(SettingError::InnerInitError)?;
 
-        s.try_deserialize()
-            .map_err(SettingError::InnerDeserializationError)
+        let mut settings: Settings = s
+            .try_deserialize()
+            .map_err(SettingError::InnerDeserializationError)?;
+        if let Some(layer) = layer {
+            settings.repository.path = layer.repository.path;
+            settings.repositories = layer.repositories;
+        }
+        settings.project = project.map(|p| p.to_path_buf());
+        settings.validate_names()?;
+        Ok(settings)
+    }
+
+    /// Repository and profile names end up in the file names of the script history,
+    /// so they are limited to letters, digits, `-` and `_`.
+    fn validate_names(&self) -> Result<(), SettingError> {
+        let names = self
+            .repositories
+            .iter()
+            .map(|named| ("repository", &named.name))
+            .chain(self.profiles.keys().map(|name| ("profile", name)));
+
+        for (kind, name) in names {
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(SettingError::InvalidName(kind, name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the project file with its paths resolved against the directory it is in,
+    /// the repository being that directory unless set otherwise.
+    fn project_layer(project: &Path) -> Result<Settings, SettingError> {
+        let invalid = |e: String| SettingError::InvalidProjectFile(project.to_path_buf(), e);
+        let directory = project.parent().unwrap_or(Path::new("."));
+        let resolve = |path: &str| directory.join(path).to_string_lossy().to_string();
+
+        let mut settings: Settings = Config::builder()
+            .add_source(File::from(project).format(FileFormat::Toml))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| invalid(e.to_string()))?;
+
+        settings.repository.path = Some(match settings.repository.path {
+            Some(ref path) => resolve(path),
+            None => directory.to_string_lossy().to_string(),
+        });
+        for named in settings.repositories.iter_mut() {
+            named.repository.path = named.repository.path.as_deref().map(resolve);
+        }
+        for profile in settings.profiles.values_mut() {
+            if let Some(ref mut repository) = profile.repository {
+                repository.path = repository.path.as_deref().map(resolve);
+            }
+        }
+        if let Some(ref mut report) = settings.report {
+            report.path = directory.join(&report.path);
+        }
+        if let Some(ref mut scaffold) = settings.scaffold {
+            scaffold.template = scaffold.template.as_ref().map(|t| directory.join(t));
+        }
+
+        Ok(settings)
+    }
+
+    /// All repositories of the session, in configured order.
+    /// The top-level `[repository]` is used when no named repositories are configured.
+    pub fn named_repositories(&self) -> Vec<NamedRepository> {
+        if self.repositories.is_empty() {
+            vec![NamedRepository {
+                name: DEFAULT_REPOSITORY.to_string(),
+                repository: self.repository.clone(),
+                database: None,
+            }]
+        } else {
+            self.repositories.clone()
+        }
+    }
+
+    /// Settings with the repository and database of `named` in place of the top-level ones.
+    /// The connection of the active profile still takes precedence over the one of `named`.
+    pub fn for_repository(&self, named: &NamedRepository) -> Settings {
+        let mut settings = self.clone();
+        settings.repository = named.repository.clone();
+        if let Some(ref database) = named.database {
+            settings.database = database.or(&self.database);
+            if let Some(profile) = self.active_profile_database() {
+                settings.database = profile.or(&settings.database);
+            }
+        }
+        settings
+    }
+
+    fn active_profile_database(&self) -> Option<&Database> {
+        self.profiles
+            .get(self.profile.as_deref()?)?
+            .database
+            .as_ref()
+    }
+
+    /// Settings with the connection and repository of the profile `name`, or of the default
+    /// profile when no name is given, in place of the top-level ones.
+    /// Settings are returned unchanged when neither is set. Profiles cannot replace the
+    /// repository when several are configured.
+    pub fn with_profile(&self, name: Option<&str>) -> Result<Settings, ProfileError> {
+        let Some(name) = name.or(self.profile.as_deref()) else {
+            return Ok(self.clone());
+        };
+        let profile = self
+            .profiles
+            .get(name)
+            .ok_or_else(|| ProfileError::UnknownProfile(name.to_string()))?;
+        if profile.repository.is_some() && !self.repositories.is_empty() {
+            return Err(ProfileError::RepositoryWithRepositories(name.to_string()));
+        }
+
+        let mut settings = self.clone();
+        settings.profile = Some(name.to_string());
+        if let Some(ref database) = profile.database {
+            settings.database = database.or(&self.database);
+        }
+        if let Some(ref repository) = profile.repository {
+            settings.repository = repository.clone();
+        }
+        Ok(settings)
     }
 
     pub fn default() -> Self {
@@ -92,28 +435,56 @@ impl Settings {
             database: Database {
                 integrated: None,
                 password: None,
+                password_env: None,
+                password_command: None,
                 port: None,
                 server: None,
                 username: None,
                 name: None,
             },
-            repository: Repository { path: None },
+            repository: Repository::default(),
+            repositories: vec![],
+            report: None,
+            lint: BTreeMap::new(),
+            scaffold: None,
+            profile: None,
+            profiles: BTreeMap::new(),
+            project: None,
         }
     }
 }
 
+/// Configuration committed along with the migrations.
+pub const PROJECT_FILE: &str = ".squealmate.toml";
+
+/// Looks for the project file in `from` and the directories above it.
+pub fn find_project_file(from: &Path) -> Option<PathBuf> {
+    from.ancestors()
+        .map(|directory| directory.join(PROJECT_FILE))
+        .find(|file| file.is_file())
+}
+
 fn project_directory() -> Option<ProjectDirs> {
     ProjectDirs::from("com", "beardo", "squealmate")
 }
 
-pub fn get_script_database() -> PathBuf {
+/// History of a repository, kept apart for every profile since each runs against its own database.
+pub fn get_script_database(repository: &str, profile: Option<&str>) -> PathBuf {
     let directory = if let Some(proj_dirs) = project_directory() {
         proj_dirs.data_local_dir().to_path_buf()
     } else {
         PathBuf::from(".")
     };
 
-    directory.join("scripts.db")
+    let name = if repository == DEFAULT_REPOSITORY {
+        "scripts".to_string()
+    } else {
+        format!("scripts-{}", repository)
+    };
+    match profile {
+        Some(profile) => directory.join(format!("{}.{}.db", name, profile)),
+        None => directory.join(format!("{}.db", name)),
+    }
 }
 
 pub fn get_data_dir() -> PathBuf {
@@ -165,3 +536,172 @@ fn path_only_simple() {
     assert!(s.is_ok());
     assert_eq!(s.unwrap().repository.path, Some("PATH".to_string()))
 }
+
+#[test]
+fn scan_rules_defaults() {
+    let s = Settings::from_path("./.tests/config/path.toml").unwrap();
+    assert!(s.repository.include.is_empty());
+    assert!(s.repository.exclude.is_empty());
+    assert_eq!(s.repository.extensions, vec!["sql"]);
+    assert_eq!(s.repository.repeatable, vec!["**/R__*"]);
+    assert_eq!(s.repository.base_ref, DEFAULT_BASE_REF);
+    assert_eq!(s.repository.rollback_suffix, "down");
+}
+
+#[test]
+fn scan_rules_simple() {
+    let s = Settings::from_path("./.tests/config/scan.toml").unwrap();
+    assert_eq!(s.repository.exclude, vec!["**/drafts", "*.rollback.sql"]);
+    assert_eq!(s.repository.extensions, vec!["sql", "tsql"]);
+    assert_eq!(s.repository.ordering, ScriptOrdering::VersionPrefix);
+    assert_eq!(s.repository.repeatable, vec!["views/**"]);
+    assert_eq!(s.repository.encoding, "windows-1250");
+}
+
+#[test]
+fn named_repositories_simple() {
+    let s = Settings::from_path("./.tests/config/repositories.toml").unwrap();
+    let named = s.named_repositories();
+    assert_eq!(2, named.len());
+    assert_eq!("core", named[0].name);
+    assert_eq!(Some("core".to_string()), named[0].repository.path);
+    assert_eq!(ScriptOrdering::Natural, named[0].repository.ordering);
+    assert_eq!(vec!["sql"], named[0].repository.extensions);
+
+    let core = s.for_repository(&named[0]);
+    assert_eq!(Some("CoreDb".to_string()), core.database.name);
+    assert_eq!(Some("dbserver".to_string()), core.database.server);
+
+    let reporting = s.for_repository(&named[1]);
+    assert_eq!(Some("SharedDb".to_string()), reporting.database.name);
+}
+
+#[test]
+fn named_repositories_default() {
+    let s = Settings::from_path("./.tests/config/path.toml").unwrap();
+    let named = s.named_repositories();
+    assert_eq!(1, named.len());
+    assert_eq!(DEFAULT_REPOSITORY, named[0].name);
+    assert_eq!(Some("PATH".to_string()), named[0].repository.path);
+}
+
+#[test]
+fn profiles_simple() {
+    let s = Settings::from_path("./.tests/config/profiles.toml").unwrap();
+    assert_eq!(Some("dev".to_string()), s.profile);
+
+    let dev = s.with_profile(None).unwrap();
+    assert_eq!(Some("dev".to_string()), dev.profile);
+    assert_eq!(Some("AppDb_Dev".to_string()), dev.database.name);
+    assert_eq!(Some("localhost".to_string()), dev.database.server);
+    assert_eq!(Some("PATH".to_string()), dev.repository.path);
+
+    let uat = s.with_profile(Some("uat")).unwrap();
+    assert_eq!(Some("AppDb_Uat".to_string()), uat.database.name);
+    assert_eq!(Some("uat-sql".to_string()), uat.database.server);
+    assert_eq!(Some("release.zip".to_string()), uat.repository.path);
+    assert_eq!(ScriptOrdering::VersionPrefix, uat.repository.ordering);
+
+    assert_eq!(
+        Err(ProfileError::UnknownProfile("prod".to_string())),
+        s.with_profile(Some("prod")).map(|_| ())
+    );
+}
+
+#[test]
+fn profiles_with_repositories() {
+    let s = Settings::from_path("./.tests/config/repositories_profiles.toml").unwrap();
+
+    let uat = s.with_profile(None).unwrap();
+    let named = uat.named_repositories();
+    let core = uat.for_repository(&named[0]);
+    assert_eq!(Some("CoreDb".to_string()), core.database.name);
+    assert_eq!(Some("uat-sql".to_string()), core.database.server);
+    let reporting = uat.for_repository(&named[1]);
+    assert_eq!(Some("SharedDb".to_string()), reporting.database.name);
+    assert_eq!(Some("uat-sql".to_string()), reporting.database.server);
+
+    // The profile is applied last, over the connection of each repository
+    let sandbox = s.with_profile(Some("sandbox")).unwrap();
+    let core = sandbox.for_repository(&sandbox.named_repositories()[0]);
+    assert_eq!(Some("SandboxDb".to_string()), core.database.name);
+    assert_eq!(Some("dbserver".to_string()), core.database.server);
+
+    assert_eq!(
+        Err(ProfileError::RepositoryWithRepositories(
+            "release".to_string()
+        )),
+        s.with_profile(Some("release")).map(|_| ())
+    );
+}
+
+#[test]
+fn profiles_none() {
+    let s = Settings::from_path("./.tests/config/path.toml").unwrap();
+    let unchanged = s.with_profile(None).unwrap();
+    assert_eq!(None, unchanged.profile);
+    assert_eq!(Some("PATH".to_string()), unchanged.repository.path);
+    assert!(s.with_profile(Some("dev")).is_err());
+}
+
+#[test]
+fn invalid_names() {
+    assert!(matches!(
+        Settings::from_path("./.tests/config/invalid_names.toml"),
+        Err(SettingError::InvalidName("repository", name)) if name == "../core"
+    ));
+    assert!(matches!(
+        Settings::from_path("./.tests/config/invalid_profile.toml"),
+        Err(SettingError::InvalidName("profile", name)) if name == "uat/eu"
+    ));
+}
+
+#[test]
+fn lint_severities() {
+    let s = Settings::from_path("./.tests/config/lint.toml").unwrap();
+    assert_eq!(Some(&Severity::Off), s.lint.get("missing-go"));
+    assert_eq!(Some(&Severity::Error), s.lint.get("unbounded-modification"));
+}
+
+#[test]
+fn project_file_discovery() {
+    let project = find_project_file(Path::new(".tests/project/nested/deeper"));
+    assert_eq!(
+        Some(PathBuf::from(".tests/project/.squealmate.toml")),
+        project
+    );
+    assert_eq!(None, find_project_file(Path::new("/")));
+}
+
+#[test]
+fn project_file_layered() {
+    let project = Path::new(".tests/project/.squealmate.toml");
+
+    let s = Settings::from_paths(Some(project), "./.tests/config/empty.toml").unwrap();
+    assert_eq!(Some(project.to_path_buf()), s.project);
+    assert_eq!(Some(".tests/project".to_string()), s.repository.path);
+    assert_eq!(ScriptOrdering::VersionPrefix, s.repository.ordering);
+    assert_eq!(Some("ProjectDb".to_string()), s.database.name);
+    assert_eq!(
+        Some(".tests/project/release.zip".to_string()),
+        s.profiles["uat"].repository.as_ref().unwrap().path
+    );
+
+    // The user's configuration wins over the project, except for where the scripts are
+    let s = Settings::from_paths(Some(project), "./.tests/config/repositories.toml").unwrap();
+    assert_eq!(Some("SharedDb".to_string()), s.database.name);
+    assert_eq!(Some("dbserver".to_string()), s.database.server);
+    assert_eq!(ScriptOrdering::VersionPrefix, s.repository.ordering);
+    assert!(s.repositories.is_empty());
+
+    let s = Settings::from_paths(Some(project), "./.tests/config/path.toml").unwrap();
+    assert_eq!(Some(".tests/project".to_string()), s.repository.path);
+}
+
+#[test]
+fn invalid_project_file() {
+    let project = Path::new(".tests/project/invalid/.squealmate.toml");
+    let error = Settings::from_paths(Some(project), "./.tests/config/empty.toml").unwrap_err();
+    assert!(matches!(error, SettingError::InvalidProjectFile(ref path, _) if path == project));
+    assert!(error.to_string().starts_with("Invalid project file"));
+}
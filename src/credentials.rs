@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    env, fmt,
+    io::{self, IsTerminal},
+    process::{Command, Stdio},
+    sync::Mutex,
+};
+
+use crate::config::Database;
+
+/// Passwords typed in this session, so that repositories sharing a login ask only once.
+static PROMPTED: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+/// Where the password of a connection comes from, in the order they are tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordSource {
+    /// Written in plain text in the configuration
+    Stored,
+    /// Read from the environment variable named by `password_env`
+    Environment,
+    /// Printed by `password_command`, such as `pass show db/dev`
+    Command,
+    /// Asked for with a masked prompt at startup
+    Prompt,
+}
+
+impl PasswordSource {
+    pub fn of(database: &Database) -> Self {
+        if database.password.is_some() {
+            PasswordSource::Stored
+        } else if database.password_env.is_some() {
+            PasswordSource::Environment
+        } else if database.password_command.is_some() {
+            PasswordSource::Command
+        } else {
+            PasswordSource::Prompt
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CredentialError {
+    MissingVariable(String),
+    CommandFailed(String, String),
+}
+
+impl fmt::Display for CredentialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialError::MissingVariable(name) => {
+                write!(
+                    f,
+                    "Environment variable {} with the DB password is not set",
+                    name
+                )
+            }
+            CredentialError::CommandFailed(command, reason) => {
+                write!(f, "Password command `{}` failed: {}", command, reason)
+            }
+        }
+    }
+}
+
+/// Password configured for `database`, looked up in the environment or printed by the
+/// password command when it is not stored in the configuration. `None` when none is set up.
+pub fn resolve(database: &Database) -> Result<Option<String>, CredentialError> {
+    match PasswordSource::of(database) {
+        PasswordSource::Stored => Ok(database.password.clone()),
+        PasswordSource::Environment => {
+            let name = database.password_env.clone().unwrap_or_default();
+            env::var(&name)
+                .map(Some)
+                .map_err(|_| CredentialError::MissingVariable(name))
+        }
+        PasswordSource::Command => {
+            run_command(database.password_command.as_deref().unwrap_or_default()).map(Some)
+        }
+        PasswordSource::Prompt => Ok(None),
+    }
+}
+
+/// Runs `command` through the shell, its standard output without the trailing line break
+/// is the password. Standard input and error stay attached so the command can ask for
+/// a passphrase itself.
+fn run_command(command: &str) -> Result<String, CredentialError> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let failed = |reason: String| CredentialError::CommandFailed(command.to_string(), reason);
+
+    let output = shell
+        .arg(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| failed(e.to_string()))?;
+    if !output.status.success() {
+        return Err(failed(output.status.to_string()));
+    }
+
+    let stdout = String::from_utf8(output.stdout).map_err(|e| failed(e.to_string()))?;
+    let password = stdout.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        return Err(failed("nothing was printed".to_string()));
+    }
+    Ok(password.to_string())
+}
+
+/// Asks for the password of `login`, written as `username@server:port`, with a masked
+/// prompt. `None` when there is nobody to ask, such as in a deployment pipeline,
+/// or when the prompt was cancelled.
+pub fn prompt(login: &str) -> Option<String> {
+    if !io::stdin().is_terminal() {
+        return None;
+    }
+
+    let key = login.to_string();
+    let mut prompted = PROMPTED.lock().ok()?;
+    let prompted = prompted.get_or_insert_with(HashMap::new);
+    if let Some(password) = prompted.get(&key) {
+        return Some(password.clone());
+    }
+
+    let password: String = cliclack::password(format!("Password of {}", key))
+        .mask('▪')
+        .interact()
+        .ok()?;
+    prompted.insert(key, password.clone());
+    Some(password)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolve_sources() {
+        let mut database = Database::default();
+        assert_eq!(PasswordSource::Prompt, PasswordSource::of(&database));
+        assert_eq!(Ok(None), resolve(&database));
+
+        database.password_command = Some("echo from-command".to_string());
+        assert_eq!(PasswordSource::Command, PasswordSource::of(&database));
+        assert_eq!(Ok(Some("from-command".to_string())), resolve(&database));
+
+        database.password_env = Some("SQUEALMATE_TEST_RESOLVE_SOURCES".to_string());
+        assert_eq!(
+            Err(CredentialError::MissingVariable(
+                "SQUEALMATE_TEST_RESOLVE_SOURCES".to_string()
+            )),
+            resolve(&database)
+        );
+        env::set_var("SQUEALMATE_TEST_RESOLVE_SOURCES", "from-env");
+        assert_eq!(Ok(Some("from-env".to_string())), resolve(&database));
+
+        database.password = Some("stored".to_string());
+        assert_eq!(Ok(Some("stored".to_string())), resolve(&database));
+    }
+
+    #[test]
+    fn failing_command() {
+        let database = Database {
+            password_command: Some("exit 3".to_string()),
+            ..Database::default()
+        };
+        assert!(matches!(
+            resolve(&database),
+            Err(CredentialError::CommandFailed(_, _))
+        ));
+    }
+}
@@ -1,11 +1,26 @@
-use std::error::Error;
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+};
 
 use tiberius::{AuthMethod, Client, Config};
 use tokio::net::TcpStream;
 use tokio_util::compat::TokioAsyncWriteCompatExt;
+use tracing::{
+    field::{Field, Visit},
+    instrument::WithSubscriber,
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
 
 use crate::batch_parser::BatchParser;
 
+/// Module of tiberius reporting informational messages of the server, PRINT output included.
+/// Tiberius leaves them out of the query results and only traces them. The module is private,
+/// so tiberius is pinned and the target has to be checked again when upgrading it.
+const SERVER_MESSAGES: &str = "tiberius::tds::stream::token";
+
 #[derive(Debug, Clone)]
 pub struct Database {
     pub server: String,
@@ -20,16 +35,19 @@ pub enum Authentication {
     SqlServer { username: String, password: String },
 }
 
+/// Splits a script into the batches sent to the database one by one.
+pub fn split_batches(script: &str) -> Vec<String> {
+    let script = script.strip_prefix('\u{feff}').unwrap_or(script);
+    BatchParser::parse(script).batches
+}
+
 impl Database {
-    pub async fn execute_script(
+    /// Executes `batches` one by one, returning informational messages of the server such as
+    /// PRINT output, and a line for every batch that returned results.
+    pub async fn execute_batches(
         &self,
-        mut script: &str,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        //let mut script = tokio::fs::read_to_string(path).await?;
-        if script.starts_with('\u{feff}') {
-            script = &script[3..];
-        }
-
+        batches: Vec<String>,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
         let mut config = Config::new();
 
         config.host(&self.server);
@@ -51,12 +69,95 @@ impl Database {
 
         let mut client = Client::connect(config, tcp.compat_write()).await?;
 
-        let parse = BatchParser::parse(script);
+        let mut output = vec![];
+        for (number, batch) in batches.into_iter().enumerate() {
+            let messages = ServerMessages::default();
+            let results = async { client.simple_query(batch).await?.into_results().await }
+                .with_subscriber(tracing_subscriber::registry().with(messages.clone()))
+                .await?;
+
+            output.extend(messages.take());
+            if !results.is_empty() {
+                let rows: usize = results.iter().map(Vec::len).sum();
+                output.push(format!(
+                    "Batch {}: {} result sets, {} rows",
+                    number + 1,
+                    results.len(),
+                    rows
+                ));
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// Collects informational messages of the server traced while a batch runs.
+#[derive(Debug, Clone, Default)]
+struct ServerMessages(Arc<Mutex<Vec<String>>>);
+
+impl ServerMessages {
+    fn take(&self) -> Vec<String> {
+        self.0
+            .lock()
+            .map(|mut messages| std::mem::take(&mut *messages))
+            .unwrap_or_default()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for ServerMessages {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() != Level::INFO || metadata.target() != SERVER_MESSAGES {
+            return;
+        }
+
+        let mut message = Message(None);
+        event.record(&mut message);
+        if let (Some(message), Ok(mut messages)) = (message.0, self.0.lock()) {
+            messages.push(message);
+        }
+    }
+}
+
+/// Text of a traced event.
+struct Message(Option<String>);
 
-        for batch in parse.batches {
-            client.simple_query(batch).await?;
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = Some(format!("{:?}", value));
         }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn server_messages() {
+        let messages = ServerMessages::default();
+        async {
+            tracing::event!(target: SERVER_MESSAGES, Level::INFO, "{}", "Rows copied: 3");
+            tracing::event!(target: SERVER_MESSAGES, Level::TRACE, "done");
+            tracing::event!(Level::INFO, "Unrelated");
+        }
+        .with_subscriber(tracing_subscriber::registry().with(messages.clone()))
+        .await;
+
+        assert_eq!(vec!["Rows copied: 3"], messages.take());
+        assert!(messages.take().is_empty());
+    }
 
-        Ok(())
+    #[test]
+    fn server_messages_target_checked() {
+        // Upgrading tiberius fails here until SERVER_MESSAGES is checked against its source
+        let lock = include_str!("../Cargo.lock");
+        assert!(
+            lock.contains("name = \"tiberius\"\nversion = \"0.12.3\"\n"),
+            "tiberius was upgraded, check that it still traces server messages from {}",
+            SERVER_MESSAGES
+        );
     }
 }
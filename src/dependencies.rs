@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use crate::source::native_path;
+
+/// Marker of a dependency declaration in the header comment of a script.
+const DEPENDS: &str = "depends:";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DependencyError {
+    /// Script depends on a path that is not a script in the repository
+    Missing { script: String, dependency: String },
+    /// Scripts depending on each other in a circle, the first one repeated at the end
+    Cycle(Vec<String>),
+}
+
+impl std::fmt::Display for DependencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencyError::Missing { script, dependency } => {
+                write!(f, "{} depends on missing {}", script, dependency)
+            }
+            DependencyError::Cycle(scripts) => {
+                write!(f, "Cyclic dependency {}", scripts.join(" -> "))
+            }
+        }
+    }
+}
+
+/// Reads dependencies declared in the leading comments of a script, e.g.
+///
+/// ```sql
+/// -- depends: core/2024/010_tables.sql, core/2024/011_keys.sql
+/// ```
+///
+/// Paths are relative to the repository root, written with either separator, the header
+/// ends with the first line that is neither empty nor a comment.
+pub fn parse_dependencies(content: &str) -> Vec<String> {
+    content
+        .trim_start_matches('\u{FEFF}')
+        .lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with("--"))
+        .filter_map(|line| {
+            let comment = line.trim_start_matches('-').trim_start();
+            let prefix = comment.get(..DEPENDS.len())?;
+            if prefix.eq_ignore_ascii_case(DEPENDS) {
+                Some(&comment[DEPENDS.len()..])
+            } else {
+                None
+            }
+        })
+        .flat_map(|list| list.split(','))
+        .map(|path| native_path(path.trim()))
+        .filter(|path| !path.is_empty())
+        .collect()
+}
+
+/// Dependencies between scripts of a single repository, keyed by paths relative to its root
+/// in their native form, so requirements match paths in the history and the run queue.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DependencyGraph {
+    dependencies: HashMap<String, Vec<String>>,
+}
+
+impl DependencyGraph {
+    /// Adds a script of the repository along with the scripts it depends on.
+    pub fn insert(&mut self, script: &str, dependencies: Vec<String>) {
+        let dependencies = dependencies.iter().map(|d| native_path(d)).collect();
+        self.dependencies.insert(native_path(script), dependencies);
+    }
+
+    pub fn dependencies_of(&self, script: &str) -> &[String] {
+        self.dependencies
+            .get(&native_path(script))
+            .map(|dependencies| dependencies.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Lists everything `script` needs, directly or through other scripts,
+    /// so that every dependency comes before the scripts needing it.
+    pub fn requirements(&self, script: &str) -> Vec<String> {
+        let mut visited = vec![native_path(script)];
+        let mut requirements = vec![];
+        self.collect_requirements(script, &mut visited, &mut requirements);
+        requirements
+    }
+
+    fn collect_requirements(
+        &self,
+        script: &str,
+        visited: &mut Vec<String>,
+        requirements: &mut Vec<String>,
+    ) {
+        for dependency in self.dependencies_of(script) {
+            if visited.contains(dependency) {
+                continue;
+            }
+            visited.push(dependency.clone());
+            self.collect_requirements(dependency, visited, requirements);
+            requirements.push(dependency.clone());
+        }
+    }
+
+    /// Finds dependencies on missing scripts and dependency cycles.
+    pub fn problems(&self) -> Vec<DependencyError> {
+        let mut scripts: Vec<&String> = self.dependencies.keys().collect();
+        scripts.sort();
+
+        let mut problems: Vec<DependencyError> = scripts
+            .iter()
+            .flat_map(|script| {
+                self.dependencies[*script]
+                    .iter()
+                    .filter(|dependency| !self.dependencies.contains_key(*dependency))
+                    .map(|dependency| DependencyError::Missing {
+                        script: script.to_string(),
+                        dependency: dependency.clone(),
+                    })
+            })
+            .collect();
+
+        let mut finished = vec![];
+        for script in scripts {
+            let mut path = vec![];
+            self.find_cycles(script, &mut path, &mut finished, &mut problems);
+        }
+
+        problems
+    }
+
+    fn find_cycles(
+        &self,
+        script: &String,
+        path: &mut Vec<String>,
+        finished: &mut Vec<String>,
+        problems: &mut Vec<DependencyError>,
+    ) {
+        if finished.contains(script) {
+            return;
+        }
+        if let Some(start) = path.iter().position(|s| s == script) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(script.clone());
+            problems.push(DependencyError::Cycle(cycle));
+            return;
+        }
+
+        path.push(script.clone());
+        for dependency in self.dependencies_of(script) {
+            self.find_cycles(dependency, path, finished, problems);
+        }
+        path.pop();
+        finished.push(script.clone());
+    }
+
+    /// Orders `scripts` so that each one comes after the scripts it depends on, keeping
+    /// the given order wherever dependencies allow. Returns indexes into `scripts`.
+    /// Dependencies outside of `scripts` are ignored and so are the edges closing a cycle.
+    pub fn order(&self, scripts: &[&str]) -> Vec<usize> {
+        let normalized: Vec<String> = scripts.iter().map(|s| native_path(s)).collect();
+        let mut visiting = vec![false; scripts.len()];
+        let mut placed = vec![false; scripts.len()];
+        let mut order = Vec::with_capacity(scripts.len());
+
+        for index in 0..scripts.len() {
+            self.place(index, &normalized, &mut visiting, &mut placed, &mut order);
+        }
+
+        order
+    }
+
+    fn place(
+        &self,
+        index: usize,
+        scripts: &[String],
+        visiting: &mut [bool],
+        placed: &mut [bool],
+        order: &mut Vec<usize>,
+    ) {
+        if placed[index] || visiting[index] {
+            return;
+        }
+
+        visiting[index] = true;
+        for dependency in self.dependencies_of(&scripts[index]) {
+            if let Some(position) = scripts.iter().position(|s| s == dependency) {
+                self.place(position, scripts, visiting, placed, order);
+            }
+        }
+        visiting[index] = false;
+
+        placed[index] = true;
+        order.push(index);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn graph(edges: &[(&str, &[&str])]) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        for (script, dependencies) in edges {
+            graph.insert(script, dependencies.iter().map(|d| d.to_string()).collect());
+        }
+        graph
+    }
+
+    #[test]
+    fn parse_header() {
+        let content = "\u{FEFF}-- Creates reports\n\
+                       -- depends: core/010_tables.sql, core/011_keys.sql\n\
+                       \n\
+                       --Depends: core\\012_views.sql\n\
+                       CREATE TABLE x (id INT)\n\
+                       -- depends: ignored.sql\n";
+
+        assert_eq!(
+            vec![
+                native_path("core/010_tables.sql"),
+                native_path("core/011_keys.sql"),
+                native_path("core/012_views.sql")
+            ],
+            parse_dependencies(content)
+        );
+        assert!(parse_dependencies("SELECT 1").is_empty());
+    }
+
+    #[test]
+    fn order_dependencies_first() {
+        let graph = graph(&[
+            ("core/001.sql", &[]),
+            ("core/002.sql", &[]),
+            ("reports/001.sql", &["core/002.sql"]),
+            ("a/001.sql", &["reports/001.sql"]),
+        ]);
+
+        let scripts = [
+            "a/001.sql",
+            "core/001.sql",
+            "core/002.sql",
+            "reports/001.sql",
+        ];
+        // Dependencies move right in front of the first script needing them
+        assert_eq!(vec![2, 3, 0, 1], graph.order(&scripts));
+
+        assert_eq!(
+            vec!["core/002.sql", "reports/001.sql"],
+            graph.requirements("a/001.sql")
+        );
+    }
+
+    #[test]
+    fn requirements_native_paths() {
+        // Keys as the repository lists scripts, with the separator of the platform
+        let separator = std::path::MAIN_SEPARATOR;
+        let core = format!("core{}001.sql", separator);
+        let keys = format!("core{}002.sql", separator);
+        let report = format!("reports{}001.sql", separator);
+
+        let mut graph = DependencyGraph::default();
+        graph.insert(&core, vec![]);
+        graph.insert(&keys, parse_dependencies("-- depends: core\\001.sql"));
+        graph.insert(&report, parse_dependencies("-- depends: core/002.sql"));
+
+        assert_eq!(
+            vec![core.clone(), keys.clone()],
+            graph.requirements(&report)
+        );
+        assert!(graph.problems().is_empty());
+        assert_eq!(
+            vec![1, 0, 2],
+            graph.order(&[keys.as_str(), core.as_str(), report.as_str()])
+        );
+    }
+
+    #[test]
+    fn order_cycle() {
+        let graph = graph(&[("a.sql", &["b.sql"]), ("b.sql", &["a.sql"])]);
+
+        assert_eq!(vec![1, 0], graph.order(&["a.sql", "b.sql"]));
+        assert_eq!(
+            vec![DependencyError::Cycle(vec![
+                "a.sql".into(),
+                "b.sql".into(),
+                "a.sql".into()
+            ])],
+            graph.problems()
+        );
+    }
+
+    #[test]
+    fn problems_missing() {
+        let graph = graph(&[("a.sql", &["missing.sql"]), ("b.sql", &["a.sql"])]);
+
+        assert_eq!(
+            vec![DependencyError::Missing {
+                script: "a.sql".into(),
+                dependency: "missing.sql".into()
+            }],
+            graph.problems()
+        );
+    }
+}
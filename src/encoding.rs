@@ -0,0 +1,117 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// Code page used for scripts that are neither UTF-8 nor UTF-16 when none is configured.
+pub const DEFAULT_FALLBACK: &str = "windows-1252";
+
+/// Script content converted to text, along with the encoding it was stored in.
+#[derive(Debug, Clone)]
+pub struct Decoded {
+    pub content: String,
+    pub encoding: &'static Encoding,
+}
+
+/// Finds the code page for a label such as `windows-1250` or `latin2`.
+pub fn fallback_from_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+}
+
+/// Converts script content to text. A byte order mark decides the encoding when present,
+/// otherwise UTF-16 is recognized by its zero bytes, valid UTF-8 is taken as it is
+/// and anything else is read using the `fallback` code page.
+pub fn decode(bytes: &[u8], fallback: &'static Encoding) -> Decoded {
+    let encoding = match Encoding::for_bom(bytes) {
+        Some((encoding, _)) => encoding,
+        None => detect(bytes, fallback),
+    };
+
+    // Removes the byte order mark of the detected encoding, if there is one
+    let (content, _) = encoding.decode_with_bom_removal(bytes);
+
+    Decoded {
+        content: content.into_owned(),
+        encoding,
+    }
+}
+
+fn detect(bytes: &[u8], fallback: &'static Encoding) -> &'static Encoding {
+    // ASCII text stored as UTF-16 has every other byte zero
+    let sample = &bytes[..bytes.len().min(512) & !1];
+    if !sample.is_empty() {
+        let pairs = sample.len() / 2;
+        let zero_high = sample.chunks(2).filter(|pair| pair[1] == 0).count();
+        let zero_low = sample.chunks(2).filter(|pair| pair[0] == 0).count();
+
+        if zero_high * 2 > pairs && zero_low == 0 {
+            return UTF_16LE;
+        }
+        if zero_low * 2 > pairs && zero_high == 0 {
+            return UTF_16BE;
+        }
+    }
+
+    if std::str::from_utf8(bytes).is_ok() {
+        UTF_8
+    } else {
+        fallback
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::{WINDOWS_1250, WINDOWS_1252};
+
+    use super::*;
+
+    fn utf16(text: &str, big_endian: bool, bom: bool) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut units: Vec<u16> = text.encode_utf16().collect();
+        if bom {
+            units.insert(0, 0xFEFF);
+        }
+        for unit in units {
+            if big_endian {
+                bytes.extend(unit.to_be_bytes());
+            } else {
+                bytes.extend(unit.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn decode_bom() {
+        let decoded = decode("\u{FEFF}SELECT 'ž'".as_bytes(), WINDOWS_1250);
+        assert_eq!(UTF_8, decoded.encoding);
+        assert_eq!("SELECT 'ž'", decoded.content);
+
+        let decoded = decode(&utf16("SELECT 'ž'", false, true), WINDOWS_1250);
+        assert_eq!(UTF_16LE, decoded.encoding);
+        assert_eq!("SELECT 'ž'", decoded.content);
+
+        let decoded = decode(&utf16("SELECT 1", true, true), WINDOWS_1250);
+        assert_eq!(UTF_16BE, decoded.encoding);
+        assert_eq!("SELECT 1", decoded.content);
+    }
+
+    #[test]
+    fn decode_without_bom() {
+        let decoded = decode(&utf16("SELECT 'ž'", false, false), WINDOWS_1250);
+        assert_eq!(UTF_16LE, decoded.encoding);
+        assert_eq!("SELECT 'ž'", decoded.content);
+
+        let decoded = decode("SELECT 'ž'".as_bytes(), WINDOWS_1250);
+        assert_eq!(UTF_8, decoded.encoding);
+
+        // "ž" in Windows-1250
+        let decoded = decode(b"SELECT '\x9e'", WINDOWS_1250);
+        assert_eq!(WINDOWS_1250, decoded.encoding);
+        assert_eq!("SELECT 'ž'", decoded.content);
+    }
+
+    #[test]
+    fn fallback_labels() {
+        assert_eq!(Some(WINDOWS_1250), fallback_from_label("windows-1250"));
+        assert_eq!(Some(WINDOWS_1252), fallback_from_label(DEFAULT_FALLBACK));
+        assert_eq!(None, fallback_from_label("klingon"));
+    }
+}
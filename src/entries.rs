@@ -1,5 +1,7 @@
 use std::fmt::Display;
 
+use crate::{git::GitStatus, lint::Finding};
+
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Hash)]
 
 pub struct ListEntry {
@@ -7,6 +9,14 @@ pub struct ListEntry {
     pub name: String,
     pub selected: bool,
     pub is_directory: bool,
+    pub repeatable: bool,
+    pub git: Option<GitStatus>,
+    /// Name of the encoding the script is stored in
+    pub encoding: Option<&'static str>,
+    /// Whether a rollback script is paired with the script
+    pub rollback: bool,
+    /// Problems found by the linter
+    pub lint: Vec<Finding>,
     pub status: EntryStatus,
 }
 
@@ -14,11 +24,26 @@ pub struct ListEntry {
 pub enum EntryStatus {
     NeverStarted,
     Finished(bool),
+    Marked,
+    /// Not run under this path, but history of the same content exists under the
+    /// candidate paths that no longer exist
+    Moved(Vec<String>),
     Changed,
     Unknown,
     Directory,
 }
 
+impl EntryStatus {
+    /// Whether the content of the script ran successfully or was marked as applied,
+    /// under its path or under the path it moved from.
+    pub fn is_applied(&self) -> bool {
+        matches!(
+            self,
+            EntryStatus::Finished(true) | EntryStatus::Marked | EntryStatus::Moved(_)
+        )
+    }
+}
+
 impl Display for ListEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(&self.name)
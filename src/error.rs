@@ -1,7 +1,11 @@
+use crate::credentials::CredentialError;
+
 #[allow(unused)]
 pub enum ArgumentsError {
     MissingUsername,
-    MissingPassword,
+    /// No password for the login, written as `username@server:port`
+    MissingPassword(String),
     MissingDBName,
     PortNotNumber,
+    Credentials(CredentialError),
 }
@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use git2::{Delta, DiffOptions, Status, StatusOptions};
+
+use crate::source::native_path;
+
+/// State of a file in the working tree of the git repository containing it.
+#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Hash)]
+pub enum GitStatus {
+    Added,
+    Modified,
+    Untracked,
+}
+
+/// Git repository containing `root`, along with `root` as seen from its working directory.
+struct Located {
+    repository: git2::Repository,
+    workdir: PathBuf,
+    root: PathBuf,
+}
+
+impl Located {
+    fn open(root: &Path) -> Result<Self, git2::Error> {
+        let root = root
+            .canonicalize()
+            .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+        let repository = git2::Repository::discover(&root)?;
+        let workdir = repository
+            .workdir()
+            .ok_or_else(|| git2::Error::from_str("Bare repositories are not supported"))?
+            .canonicalize()
+            .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+
+        Ok(Self {
+            repository,
+            workdir,
+            root,
+        })
+    }
+
+    /// Converts a path reported by git into a path relative to the root, skipping files
+    /// outside of it. Git always separates with `/`, the result uses the native separator.
+    fn relative(&self, path: &Path) -> Option<String> {
+        self.workdir
+            .join(path)
+            .strip_prefix(&self.root)
+            .ok()?
+            .to_str()
+            .map(native_path)
+    }
+}
+
+/// Name of the author from the git configuration of the repository containing `root`,
+/// or from the global one.
+pub fn user_name(root: &Path) -> Option<String> {
+    let config = match git2::Repository::discover(root) {
+        Ok(repository) => repository.config().ok()?,
+        Err(_) => git2::Config::open_default().ok()?,
+    };
+    config.get_string("user.name").ok()
+}
+
+/// Status of every added, modified or untracked file below `root`,
+/// keyed by paths relative to `root`.
+pub fn statuses(root: &Path) -> Result<HashMap<String, GitStatus>, git2::Error> {
+    let located = Located::open(root)?;
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+
+    let statuses = located.repository.statuses(Some(&mut options))?;
+
+    Ok(statuses
+        .iter()
+        .filter_map(|entry| {
+            let status = entry.status();
+            let status = if status.contains(Status::WT_NEW) {
+                GitStatus::Untracked
+            } else if status.contains(Status::INDEX_NEW) {
+                GitStatus::Added
+            } else if status.intersects(
+                Status::INDEX_MODIFIED
+                    | Status::WT_MODIFIED
+                    | Status::INDEX_RENAMED
+                    | Status::WT_RENAMED
+                    | Status::INDEX_TYPECHANGE
+                    | Status::WT_TYPECHANGE,
+            ) {
+                GitStatus::Modified
+            } else {
+                return None;
+            };
+
+            let relative = located.relative(Path::new(entry.path()?))?;
+            Some((relative, status))
+        })
+        .collect())
+}
+
+/// Lists files below `root` added or modified after `since` and up to `until`,
+/// as paths relative to `root`. Without `until` the working tree is compared,
+/// including uncommitted and untracked files.
+///
+/// When both refs are commits, the comparison starts where their histories meet,
+/// so `since` may be a branch that moved on in the meantime.
+pub fn changed_between(
+    root: &Path,
+    since: &str,
+    until: Option<&str>,
+) -> Result<Vec<String>, git2::Error> {
+    let located = Located::open(root)?;
+    let repository = &located.repository;
+
+    let since = repository.revparse_single(since)?.peel_to_commit()?;
+    let target = match until {
+        Some(until) => Some(repository.revparse_single(until)?.peel_to_commit()?),
+        None => None,
+    };
+    let head = match target {
+        Some(ref target) => Some(target.id()),
+        None => repository.head().ok().and_then(|head| head.target()),
+    };
+    let start = match head.map(|head| repository.merge_base(since.id(), head)) {
+        Some(Ok(base)) => repository.find_commit(base)?,
+        _ => since,
+    };
+    let start = start.tree()?;
+
+    let diff = match target {
+        Some(target) => repository.diff_tree_to_tree(Some(&start), Some(&target.tree()?), None)?,
+        None => {
+            let mut options = DiffOptions::new();
+            options.include_untracked(true).recurse_untracked_dirs(true);
+            repository.diff_tree_to_workdir_with_index(Some(&start), Some(&mut options))?
+        }
+    };
+
+    Ok(diff
+        .deltas()
+        .filter(|delta| {
+            matches!(
+                delta.status(),
+                Delta::Added | Delta::Modified | Delta::Renamed | Delta::Copied | Delta::Untracked
+            )
+        })
+        .filter_map(|delta| located.relative(delta.new_file().path()?))
+        .collect())
+}
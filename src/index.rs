@@ -0,0 +1,333 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+    sync::{Arc, Condvar, Mutex, RwLock},
+    thread,
+    time::SystemTime,
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    lint::Finding,
+    repository::{ScanRules, ScriptOrdering},
+    source::ScriptSource,
+};
+
+/// File or directory of the repository as it was last seen on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    /// Path relative to the repository root
+    pub relative: String,
+    pub is_file: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// Components of the relative path, compared one by one to order the entries
+    key: Vec<String>,
+}
+
+impl IndexEntry {
+    fn new(relative: String, is_file: bool, size: u64, modified: Option<SystemTime>) -> Self {
+        let key = relative
+            .split(MAIN_SEPARATOR)
+            .filter(|c| !c.is_empty())
+            .map(|c| c.to_string())
+            .collect();
+        Self {
+            is_file,
+            size,
+            modified,
+            relative,
+            key,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.key.last().map_or("", |name| name.as_str())
+    }
+
+    fn compare(&self, other: &Self, ordering: ScriptOrdering) -> Ordering {
+        for (a, b) in self.key.iter().zip(other.key.iter()) {
+            match ordering.compare(a, b) {
+                Ordering::Equal => continue,
+                different => return different,
+            }
+        }
+        self.key.len().cmp(&other.key.len())
+    }
+
+    fn is_below(&self, relative: &str) -> bool {
+        relative.is_empty()
+            || self.relative == relative
+            || self
+                .relative
+                .strip_prefix(relative)
+                .is_some_and(|rest| rest.starts_with(MAIN_SEPARATOR))
+    }
+}
+
+/// Walks `from` below `root`, returning every entry that is not excluded by `rules`
+/// in repository order, `from` itself included. Excluded directories are skipped altogether.
+pub fn scan(
+    root: &Path,
+    from: &Path,
+    rules: &ScanRules,
+    ordering: ScriptOrdering,
+    max_depth: Option<usize>,
+) -> Vec<IndexEntry> {
+    let relative = |path: &Path| -> Option<String> {
+        path.strip_prefix(root)
+            .ok()?
+            .to_str()
+            .map(|f| f.to_string())
+    };
+
+    let mut walk = WalkDir::new(from).sort_by(move |a, b| {
+        ordering.compare(
+            &a.file_name().to_string_lossy(),
+            &b.file_name().to_string_lossy(),
+        )
+    });
+    if let Some(depth) = max_depth {
+        walk = walk.max_depth(depth);
+    }
+
+    walk.into_iter()
+        .filter_entry(|e| relative(e.path()).is_some_and(|r| !rules.is_excluded(&r)))
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let relative = relative(e.path())?;
+            let metadata = e.metadata().ok();
+            Some(IndexEntry::new(
+                relative,
+                e.file_type().is_file(),
+                metadata.as_ref().map_or(0, |m| m.len()),
+                metadata.and_then(|m| m.modified().ok()),
+            ))
+        })
+        .collect()
+}
+
+/// Same as [`scan`] for a listing of relative paths, file flags and sizes that does not come
+/// from the disk, such as the content of an archive. `from` is relative to the repository root.
+pub fn from_listing<'a>(
+    listing: impl Iterator<Item = (&'a str, bool, u64)>,
+    from: &str,
+    rules: &ScanRules,
+    ordering: ScriptOrdering,
+    max_depth: Option<usize>,
+) -> Vec<IndexEntry> {
+    let root = IndexEntry::new(from.to_string(), false, 0, None);
+    let depth = root.key.len();
+
+    let mut entries: Vec<IndexEntry> = listing
+        .map(|(relative, is_file, size)| IndexEntry::new(relative.to_string(), is_file, size, None))
+        .filter(|entry| entry.relative != from && entry.is_below(from))
+        .filter(|entry| max_depth.is_none_or(|max| entry.key.len() - depth <= max))
+        .filter(|entry| {
+            !Path::new(&entry.relative)
+                .ancestors()
+                .filter_map(|ancestor| ancestor.to_str())
+                .any(|ancestor| rules.is_excluded(ancestor))
+        })
+        .collect();
+    entries.push(root);
+
+    entries.sort_by(|a, b| a.compare(b, ordering));
+    entries
+}
+
+/// What was learned by reading a script, kept as long as its size and modification time
+/// stay the same.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptInfo {
+    /// Name of the encoding the script is stored in
+    pub encoding: &'static str,
+    pub lint: Vec<Finding>,
+    /// Scripts declared in the header as dependencies
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Inspected {
+    size: u64,
+    modified: Option<SystemTime>,
+    info: ScriptInfo,
+}
+
+/// In-memory listing of the whole repository, shared between the repository and the thread
+/// building it. Queries return `None` until the first build finishes.
+#[derive(Debug, Clone, Default)]
+pub struct RepositoryIndex {
+    entries: Arc<RwLock<Option<Vec<IndexEntry>>>>,
+    /// Whether a build is running, waited for with the condition variable
+    building: Arc<(Mutex<bool>, Condvar)>,
+    /// Content of scripts read so far, keyed by their relative path
+    inspected: Arc<RwLock<HashMap<String, Inspected>>>,
+}
+
+impl RepositoryIndex {
+    /// Builds the index in the background.
+    pub fn build(
+        &self,
+        source: ScriptSource,
+        root: PathBuf,
+        rules: ScanRules,
+        ordering: ScriptOrdering,
+    ) -> thread::JoinHandle<()> {
+        let entries = self.entries.clone();
+        let building = self.building.clone();
+        if let Ok(mut running) = building.0.lock() {
+            *running = true;
+        }
+
+        thread::spawn(move || {
+            let mut scanned = source.scan(&root, &root, &rules, ordering, None);
+            scanned.retain(|entry| !entry.relative.is_empty());
+            log::info!("Repository index built with {} entries", scanned.len());
+
+            if let Ok(mut entries) = entries.write() {
+                *entries = Some(scanned);
+            }
+
+            let (running, finished) = &*building;
+            if let Ok(mut running) = running.lock() {
+                *running = false;
+            }
+            finished.notify_all();
+        })
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.entries.read().is_ok_and(|entries| entries.is_some())
+    }
+
+    /// Blocks until a running build finishes, returns right away when none was started.
+    pub fn wait(&self) {
+        let (running, finished) = &*self.building;
+        if let Ok(guard) = running.lock() {
+            drop(finished.wait_while(guard, |running| *running));
+        }
+    }
+
+    /// What was learned about the script at `entry` when it was last read, unless it changed since.
+    pub fn inspected(&self, entry: &IndexEntry) -> Option<ScriptInfo> {
+        let inspected = self.inspected.read().ok()?;
+        inspected
+            .get(&entry.relative)
+            .filter(|known| known.size == entry.size && known.modified == entry.modified)
+            .map(|known| known.info.clone())
+    }
+
+    /// Keeps what was learned by reading the script at `entry`.
+    pub fn remember(&self, entry: &IndexEntry, info: ScriptInfo) {
+        if let Ok(mut inspected) = self.inspected.write() {
+            inspected.insert(
+                entry.relative.clone(),
+                Inspected {
+                    size: entry.size,
+                    modified: entry.modified,
+                    info,
+                },
+            );
+        }
+    }
+
+    /// Entries below `relative`, including it, in repository order.
+    pub fn subtree(&self, relative: &str) -> Option<Vec<IndexEntry>> {
+        let entries = self.entries.read().ok()?;
+        Some(
+            entries
+                .as_ref()?
+                .iter()
+                .filter(|entry| entry.is_below(relative))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Direct children of the directory at `relative`, in repository order.
+    pub fn children(&self, relative: &str) -> Option<Vec<IndexEntry>> {
+        let depth = if relative.is_empty() {
+            1
+        } else {
+            relative.split(MAIN_SEPARATOR).count() + 1
+        };
+
+        Some(
+            self.subtree(relative)?
+                .into_iter()
+                .filter(|entry| entry.key.len() == depth)
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, relative: &str) -> Option<IndexEntry> {
+        let entries = self.entries.read().ok()?;
+        entries
+            .as_ref()?
+            .iter()
+            .find(|entry| entry.relative == relative)
+            .cloned()
+    }
+
+    /// Re-reads the `changed` paths from disk, returning those that were added, removed
+    /// or differ in size or modification time from what the index knew about them.
+    /// Does nothing until the index is built, the build picks up the changes itself.
+    pub fn update(
+        &self,
+        source: &ScriptSource,
+        root: &Path,
+        rules: &ScanRules,
+        ordering: ScriptOrdering,
+        changed: &[String],
+    ) -> Vec<String> {
+        let Ok(mut guard) = self.entries.write() else {
+            return changed.to_vec();
+        };
+        let Some(entries) = guard.as_mut() else {
+            return changed.to_vec();
+        };
+
+        let mut modified = vec![];
+        for path in changed {
+            let (removed, kept): (Vec<IndexEntry>, Vec<IndexEntry>) = std::mem::take(entries)
+                .into_iter()
+                .partition(|entry| entry.is_below(path));
+            *entries = kept;
+
+            let excluded = Path::new(path)
+                .ancestors()
+                .filter_map(|ancestor| ancestor.to_str())
+                .any(|ancestor| rules.is_excluded(ancestor));
+            let scanned = if !excluded && source.exists(path) {
+                source.scan(root, &root.join(path), rules, ordering, None)
+            } else {
+                vec![]
+            };
+
+            for entry in scanned.iter() {
+                let unchanged = removed.iter().any(|old| {
+                    old.relative == entry.relative
+                        && old.size == entry.size
+                        && old.modified == entry.modified
+                });
+                if !unchanged && !modified.contains(&entry.relative) {
+                    modified.push(entry.relative.clone());
+                }
+            }
+            for old in removed.iter() {
+                let gone = !scanned.iter().any(|e| e.relative == old.relative);
+                if gone && !modified.contains(&old.relative) {
+                    modified.push(old.relative.clone());
+                }
+            }
+
+            entries.extend(scanned);
+        }
+
+        entries.sort_by(|a, b| a.compare(b, ordering));
+        modified
+    }
+}
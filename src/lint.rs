@@ -0,0 +1,557 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::split_batches;
+
+/// Static check run on the batches of a script.
+#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Hash)]
+pub enum Rule {
+    /// `CREATE PROCEDURE`, `VIEW`, `FUNCTION` or `TRIGGER` preceded by other statements
+    CreateNotFirst,
+    /// `USE` switching away from the database the script runs against
+    UseDatabase,
+    /// `DELETE` or `UPDATE` without a `WHERE` clause
+    UnboundedModification,
+    /// Script not ending with `GO`
+    MissingGo,
+    /// `CREATE TABLE` not guarded by an `IF` naming the table in the same batch
+    CreateTableNotIdempotent,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 5] = [
+        Rule::CreateNotFirst,
+        Rule::UseDatabase,
+        Rule::UnboundedModification,
+        Rule::MissingGo,
+        Rule::CreateTableNotIdempotent,
+    ];
+
+    /// Name of the rule in the `[lint]` section of the configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::CreateNotFirst => "create-not-first",
+            Rule::UseDatabase => "use-database",
+            Rule::UnboundedModification => "unbounded-modification",
+            Rule::MissingGo => "missing-go",
+            Rule::CreateTableNotIdempotent => "create-table-not-idempotent",
+        }
+    }
+
+    fn default_severity(&self) -> Severity {
+        match self {
+            Rule::CreateNotFirst | Rule::UseDatabase => Severity::Error,
+            Rule::UnboundedModification | Rule::MissingGo | Rule::CreateTableNotIdempotent => {
+                Severity::Warning
+            }
+        }
+    }
+}
+
+#[derive(
+    Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Off,
+    #[default]
+    Warning,
+    Error,
+}
+
+/// Problem found in a script, `line` counts from 1.
+#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Hash)]
+pub struct Finding {
+    pub line: usize,
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} [{}]", self.line, self.message, self.rule.name())
+    }
+}
+
+/// Runs every rule not turned off, with severities configured in `[lint]`.
+#[derive(Debug, Clone)]
+pub struct Linter {
+    severities: BTreeMap<Rule, Severity>,
+    /// Database the scripts run against, when it is configured
+    database: Option<String>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self {
+            severities: Rule::ALL
+                .iter()
+                .map(|rule| (*rule, rule.default_severity()))
+                .collect(),
+            database: None,
+        }
+    }
+}
+
+impl Linter {
+    /// Overrides the default severities with the configured ones, failing on unknown rule names.
+    pub fn from_settings(settings: &BTreeMap<String, Severity>) -> Result<Self, String> {
+        let mut linter = Self::default();
+        for (name, severity) in settings {
+            let rule = Rule::ALL
+                .iter()
+                .find(|rule| rule.name() == name)
+                .ok_or_else(|| name.clone())?;
+            linter.severities.insert(*rule, *severity);
+        }
+        Ok(linter)
+    }
+
+    /// Lets `USE` of the database the scripts run against pass.
+    pub fn with_database(mut self, database: Option<String>) -> Self {
+        self.database = database;
+        self
+    }
+
+    pub fn severity(&self, rule: Rule) -> Severity {
+        self.severities.get(&rule).copied().unwrap_or_default()
+    }
+
+    /// Findings of `script` in the order of their lines.
+    pub fn lint(&self, script: &str) -> Vec<Finding> {
+        let batches = split_batches(script);
+        let mut findings = vec![];
+        let mut first_line = 1;
+        let mut last: Vec<Located> = vec![];
+
+        for batch in batches.iter() {
+            let tokens = tokenize(batch, first_line);
+            first_line += batch.matches('\n').count();
+
+            self.check_batch(&tokens, &mut findings);
+            last = tokens;
+        }
+
+        if let Some(token) = last.last() {
+            self.report(
+                &mut findings,
+                Rule::MissingGo,
+                token.line,
+                "Script does not end with GO".to_string(),
+            );
+        }
+
+        findings.sort();
+        findings
+    }
+
+    fn report(&self, findings: &mut Vec<Finding>, rule: Rule, line: usize, message: String) {
+        let severity = self.severity(rule);
+        if severity != Severity::Off {
+            findings.push(Finding {
+                line,
+                rule,
+                severity,
+                message,
+            });
+        }
+    }
+
+    fn check_batch(&self, tokens: &[Located], findings: &mut Vec<Finding>) {
+        for (i, located) in tokens.iter().enumerate() {
+            let Token::Word(ref word) = located.token else {
+                continue;
+            };
+            match word.as_str() {
+                // `CREATE OR ALTER` is checked from its `CREATE`
+                "ALTER" if i > 0 && is_word(tokens.get(i - 1), "OR") => {}
+                "CREATE" | "ALTER" => self.check_create(tokens, i, findings),
+                "USE" => self.check_use(tokens, i, findings),
+                "DELETE" | "UPDATE" => self.check_modification(tokens, i, findings),
+                _ => {}
+            }
+        }
+    }
+
+    /// Modules have to be alone in their batch, tables should only be created when missing.
+    fn check_create(&self, tokens: &[Located], i: usize, findings: &mut Vec<Finding>) {
+        let mut kind = i + 1;
+        if is_word(tokens.get(kind), "OR") && is_word(tokens.get(kind + 1), "ALTER") {
+            kind += 2;
+        }
+        let Some(Token::Word(ref object)) = tokens.get(kind).map(|t| &t.token) else {
+            return;
+        };
+        let statement = format!("{} {}", tokens[i].text.to_uppercase(), object);
+
+        let is_module = matches!(
+            object.as_str(),
+            "PROCEDURE" | "PROC" | "FUNCTION" | "TRIGGER" | "VIEW"
+        );
+        if is_module && i != 0 {
+            self.report(
+                findings,
+                Rule::CreateNotFirst,
+                tokens[i].line,
+                format!("{} must be the only statement of its batch", statement),
+            );
+        }
+
+        if statement == "CREATE TABLE" {
+            let name = object_name(&tokens[kind + 1..]);
+            let temporary = name.starts_with('#');
+            let guarded = tokens[..i]
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| t.token == Token::word("IF"))
+                .any(|(start, _)| mentions(&tokens[start + 1..i], &name));
+            if !temporary && !guarded {
+                self.report(
+                    findings,
+                    Rule::CreateTableNotIdempotent,
+                    tokens[i].line,
+                    format!("CREATE TABLE {} fails when the table exists", name),
+                );
+            }
+        }
+    }
+
+    /// `USE HINT` and `USE PLAN` are query hints, not a change of database.
+    /// Without a configured database any `USE` may leave the one the script runs against.
+    fn check_use(&self, tokens: &[Located], i: usize, findings: &mut Vec<Finding>) {
+        if is_word(tokens.get(i + 1), "HINT") || is_word(tokens.get(i + 1), "PLAN") {
+            return;
+        }
+        let name = object_name(&tokens[i + 1..]);
+        let message = match self.database {
+            Some(ref database) if unquoted(&name).eq_ignore_ascii_case(database) => return,
+            Some(ref database) => format!("USE {} leaves the target database {}", name, database),
+            None => format!(
+                "USE {} overrides the database the script runs against",
+                name
+            ),
+        };
+        self.report(findings, Rule::UseDatabase, tokens[i].line, message);
+    }
+
+    /// Skips the keywords in triggers, permissions, foreign keys and merges,
+    /// as well as `UPDATE(column)` and `UPDATE STATISTICS`.
+    fn check_modification(&self, tokens: &[Located], i: usize, findings: &mut Vec<Finding>) {
+        const CLAUSES: [&str; 8] = [
+            "ON", "FOR", "AFTER", "OF", "GRANT", "DENY", "REVOKE", "THEN",
+        ];
+        let clause = i.checked_sub(1).is_some_and(|p| match tokens[p].token {
+            Token::Word(ref word) => CLAUSES.contains(&word.as_str()),
+            Token::Symbol(',') => true,
+            _ => false,
+        });
+        let call = matches!(
+            tokens.get(i + 1).map(|t| &t.token),
+            Some(Token::Symbol('('))
+        ) || is_word(tokens.get(i + 1), "STATISTICS");
+
+        let statement = tokens[i].text.to_uppercase();
+        if !clause && !call && !has_where(&tokens[i + 1..], statement == "UPDATE") {
+            self.report(
+                findings,
+                Rule::UnboundedModification,
+                tokens[i].line,
+                format!("{} without WHERE changes every row", statement),
+            );
+        }
+    }
+}
+
+/// Keywords starting another statement, ending the one before them.
+const STATEMENTS: [&str; 21] = [
+    "INSERT", "UPDATE", "DELETE", "MERGE", "CREATE", "ALTER", "DROP", "EXEC", "EXECUTE", "PRINT",
+    "IF", "ELSE", "BEGIN", "END", "COMMIT", "ROLLBACK", "DECLARE", "TRUNCATE", "RETURN", "USE",
+    "SELECT",
+];
+
+/// Whether the statement starting at `tokens` has a `WHERE` of its own, not one of a subquery.
+/// `UPDATE` statements own the first `SET` following them.
+fn has_where(tokens: &[Located], is_update: bool) -> bool {
+    let mut depth = 0;
+    let mut owns_set = is_update;
+    for located in tokens {
+        match located.token {
+            Token::Symbol('(') => depth += 1,
+            Token::Symbol(')') if depth == 0 => return false,
+            Token::Symbol(')') => depth -= 1,
+            Token::Symbol(';') if depth == 0 => return false,
+            Token::Word(ref word) if depth == 0 => {
+                if word == "WHERE" {
+                    return true;
+                }
+                if word == "SET" && owns_set {
+                    owns_set = false;
+                } else if word == "SET" || STATEMENTS.contains(&word.as_str()) {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Keyword or plain identifier, uppercased
+    Word(String),
+    /// Identifier quoted with brackets or double quotes
+    Quoted(String),
+    /// String literal, by its content
+    Literal(String),
+    Symbol(char),
+}
+
+impl Token {
+    fn word(word: &str) -> Self {
+        Token::Word(word.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Located {
+    token: Token,
+    /// Text of the token as written, literals are shortened
+    text: String,
+    line: usize,
+}
+
+fn is_word(located: Option<&Located>, word: &str) -> bool {
+    located.is_some_and(|l| l.token == Token::word(word))
+}
+
+/// Possibly qualified name such as `[dbo].Users` starting at `tokens`.
+fn object_name(tokens: &[Located]) -> String {
+    let mut name = String::new();
+    let mut expects_part = true;
+    for located in tokens {
+        match located.token {
+            Token::Word(_) | Token::Quoted(_) if expects_part => expects_part = false,
+            Token::Symbol('.') if !expects_part => expects_part = true,
+            _ => break,
+        }
+        name.push_str(&located.text);
+    }
+    name
+}
+
+/// Whether the condition starting at `tokens` refers to the object `name`, by its name
+/// or by a string such as `'dbo.Users'`. Schemas are left out of the comparison.
+fn mentions(tokens: &[Located], name: &str) -> bool {
+    let object = unqualified(name);
+    let mut depth = 0;
+    for located in tokens {
+        let part = match located.token {
+            Token::Symbol('(') => {
+                depth += 1;
+                continue;
+            }
+            Token::Symbol(')') => {
+                depth -= 1;
+                continue;
+            }
+            // The condition ends with the statement it guards
+            Token::Word(ref word)
+                if depth <= 0 && word != "SELECT" && STATEMENTS.contains(&word.as_str()) =>
+            {
+                return false;
+            }
+            Token::Word(_) | Token::Quoted(_) => unquoted(&located.text),
+            Token::Literal(ref content) => unqualified(content),
+            _ => continue,
+        };
+        if part.eq_ignore_ascii_case(object) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Last part of a possibly qualified name, without quotes.
+fn unqualified(name: &str) -> &str {
+    unquoted(name.rsplit('.').next().unwrap_or_default())
+}
+
+/// Name without the brackets or double quotes around it.
+fn unquoted(name: &str) -> &str {
+    name.trim_start_matches(['[', '"'])
+        .trim_end_matches([']', '"'])
+}
+
+/// Splits a batch into tokens, skipping comments and the content of string literals.
+fn tokenize(sql: &str, first_line: usize) -> Vec<Located> {
+    let mut tokens = vec![];
+    let mut line = first_line;
+    let mut chars = sql.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let start = line;
+        let mut text = c.to_string();
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '-' if chars.peek() == Some(&'-') => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('*') if chars.next_if_eq(&'/').is_some() => depth -= 1,
+                        Some('/') if chars.next_if_eq(&'*').is_some() => depth += 1,
+                        Some('\n') => line += 1,
+                        Some(_) => {}
+                        None => break,
+                    }
+                }
+                continue;
+            }
+            '\'' => {
+                let mut content = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.next_if_eq(&'\'').is_none() => break,
+                        Some('\n') => {
+                            line += 1;
+                            content.push('\n');
+                        }
+                        Some(c) => content.push(c),
+                        None => break,
+                    }
+                }
+                text = "'...'".to_string();
+                Token::Literal(content)
+            }
+            '[' | '"' => {
+                let end = if c == '[' { ']' } else { '"' };
+                let mut quoted = String::new();
+                for c in chars.by_ref() {
+                    if c == end {
+                        break;
+                    }
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    quoted.push(c);
+                }
+                text = format!("{}{}{}", c, quoted, end);
+                Token::Quoted(quoted)
+            }
+            c if c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '$') => {
+                while let Some(c) =
+                    chars.next_if(|c| c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '$'))
+                {
+                    text.push(c);
+                }
+                Token::Word(text.to_uppercase())
+            }
+            c => Token::Symbol(c),
+        };
+        tokens.push(Located {
+            token,
+            text,
+            line: start,
+        });
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules(script: &str) -> Vec<(usize, Rule)> {
+        Linter::default()
+            .lint(script)
+            .into_iter()
+            .map(|finding| (finding.line, finding.rule))
+            .collect()
+    }
+
+    #[test]
+    fn clean_script() {
+        let script = "IF OBJECT_ID('dbo.Users') IS NULL\n\
+                      CREATE TABLE dbo.Users (Id INT)\n\
+                      GO\n\
+                      CREATE OR ALTER PROCEDURE dbo.Clean AS\n\
+                      DELETE FROM dbo.Users WHERE Id IN (SELECT Id FROM dbo.Old)\n\
+                      UPDATE dbo.Users SET Id = (SELECT 1) WHERE Id = 2\n\
+                      -- DELETE FROM dbo.Users\n\
+                      SELECT 'USE Other' OPTION (USE HINT ('RECOMPILE'))\n\
+                      GO\n";
+        assert_eq!(Vec::<(usize, Rule)>::new(), rules(script));
+    }
+
+    #[test]
+    fn every_rule() {
+        let script = "USE Other\n\
+                      CREATE TABLE dbo.Users (Id INT)\n\
+                      CREATE VIEW dbo.Names AS SELECT 1 AS Id\n\
+                      GO\n\
+                      /* comment\n\
+                      spanning lines */ DELETE FROM dbo.Users\n\
+                      UPDATE dbo.Users SET Id = 1;\n\
+                      CREATE TABLE #temp (Id INT)";
+        assert_eq!(
+            vec![
+                (1, Rule::UseDatabase),
+                (2, Rule::CreateTableNotIdempotent),
+                (3, Rule::CreateNotFirst),
+                (6, Rule::UnboundedModification),
+                (7, Rule::UnboundedModification),
+                (8, Rule::MissingGo),
+            ],
+            rules(script)
+        );
+    }
+
+    #[test]
+    fn create_table_guards() {
+        let script = "IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'Users')\n\
+                      CREATE TABLE [dbo].[Users] (Id INT)\n\
+                      IF OBJECT_ID('dbo.Orders') IS NULL PRINT 'Creating orders'\n\
+                      CREATE TABLE dbo.Invoices (Id INT)\n\
+                      GO\n";
+        assert_eq!(vec![(4, Rule::CreateTableNotIdempotent)], rules(script));
+    }
+
+    #[test]
+    fn configured_severities() {
+        let settings = BTreeMap::from([
+            ("missing-go".to_string(), Severity::Off),
+            ("unbounded-modification".to_string(), Severity::Error),
+        ]);
+        let linter = Linter::from_settings(&settings).unwrap();
+        let findings = linter.lint("DELETE FROM dbo.Users");
+        assert_eq!(1, findings.len());
+        assert_eq!(Severity::Error, findings[0].severity);
+
+        let linter = Linter::default().with_database(Some("AppDb".to_string()));
+        let findings = linter.lint("USE [appdb]\nGO\nUSE master\nGO\n");
+        assert_eq!(1, findings.len());
+        assert_eq!(3, findings[0].line);
+        assert_eq!(
+            "USE master leaves the target database AppDb",
+            findings[0].message
+        );
+
+        let unknown = BTreeMap::from([("no-such-rule".to_string(), Severity::Off)]);
+        assert_eq!(
+            Err("no-such-rule".to_string()),
+            Linter::from_settings(&unknown).map(|_| ())
+        );
+    }
+}
@@ -3,102 +3,844 @@ mod app;
 mod batch_parser;
 mod cli;
 mod components;
+mod compression;
 mod config;
+mod credentials;
 mod db;
+mod dependencies;
+mod encoding;
 mod entries;
 mod error;
+mod git;
+mod index;
+mod lint;
+mod manifest;
+mod report;
 mod repository;
+mod runner;
+mod scaffold;
 mod screen;
 mod script_memory;
+mod source;
+mod status;
 mod tui;
 mod utils;
+mod watcher;
+mod workspace;
 
 use crate::screen::{Mode, Screen};
 
-use crate::app::App;
+use crate::app::{App, AppState, QueueOrder, Script, ScriptState};
 use crate::components::list::List;
 use clap::Parser;
-use cli::{Command, SquealMateArgs};
-use cliclack::{confirm, input, intro, outro};
+use cli::{Command, ConnectionArgs, RunArgs, SelectionArgs, SquealMateArgs};
+use cliclack::{confirm, input, intro, outro, password, select};
+use credentials::PasswordSource;
 
 use color_eyre::eyre;
+use components::confirm::Confirm;
 use components::help::Help;
+use components::orphans::Orphans;
 use components::script_status::ScriptStatus;
 use components::scroll_list::ScrollList;
-use config::{get_config_dir, get_data_dir, Settings};
+use config::{get_config_dir, get_data_dir, NamedRepository, Settings};
 use crossterm::style::Stylize;
 use crossterm::{execute, style::Print};
 use db::Database;
+use entries::EntryStatus;
 use error::ArgumentsError;
-use repository::{Repository, RepositoryError};
-use script_memory::ScriptDatabase;
+use lint::{Linter, Severity};
+use manifest::Manifest;
+use report::Report;
+use repository::{Repository, RepositoryError, ScanRules, ScriptOrdering};
+use script_memory::{checksum, ScriptDatabase};
+use status::{OutputFormat, State, StatusReport};
 use std::env;
 use std::io::{self, stdout};
 use std::path::Path;
 use std::{io::Write, path::PathBuf, str::FromStr};
 use utils::{initialize_logging, initialize_panic_handler};
+use workspace::Workspace;
 
-async fn start_tui(config: Settings, connection: Database) -> eyre::Result<()> {
-    initialize_logging()?;
+/// Exit code of the status command when some scripts still have to run,
+/// telling them apart from errors.
+const PENDING_EXIT_CODE: i32 = 2;
 
-    initialize_panic_handler()?;
+/// Manifest the queue is saved to when none was given on the command line.
+const DEFAULT_MANIFEST: &str = "manifest.toml";
 
-    let path: PathBuf = if let Some(ref content) = config.repository.path {
+fn repository_path(config: &Settings) -> PathBuf {
+    if let Some(ref content) = config.repository.path {
         PathBuf::from(content)
     } else {
         PathBuf::from_str("./").expect("Can't open current directory")
+    }
+}
+
+fn open_repository(config: &Settings) -> Result<Repository, RepositoryError> {
+    let rules = ScanRules::from_settings(&config.repository)?;
+    let linter = Linter::from_settings(&config.lint)
+        .map_err(RepositoryError::UnknownLintRule)?
+        .with_database(config.database.name.clone());
+    let encoding = encoding::fallback_from_label(&config.repository.encoding)
+        .ok_or_else(|| RepositoryError::InvalidEncoding(config.repository.encoding.clone()))?;
+
+    Ok(Repository::new(repository_path(config))?
+        .with_rules(rules)
+        .with_ordering(config.repository.ordering)
+        .with_base_ref(config.repository.base_ref.clone())
+        .with_encoding(encoding)
+        .with_linter(linter))
+}
+
+fn connect(args: &ConnectionArgs, config: &Settings) -> Option<Database> {
+    match args.merge(config) {
+        Ok(conn) => return Some(conn),
+        Err(ArgumentsError::MissingPassword(login)) => {
+            if let Some(password) = credentials::prompt(&login) {
+                let mut config = config.clone();
+                config.database.password = Some(password);
+                return connect(args, &config);
+            }
+            println!("ERROR: Missing DB password");
+        }
+        Err(ArgumentsError::Credentials(e)) => {
+            println!("ERROR: {}", e);
+        }
+        Err(ArgumentsError::MissingUsername) => {
+            println!("ERROR: Missing DB username");
+        }
+        Err(ArgumentsError::MissingDBName) => {
+            println!("ERROR: Missing DB name");
+        }
+        Err(ArgumentsError::PortNotNumber) => {
+            println!("ERROR: Supplied port is not a valid number");
+        }
+    };
+    None
+}
+
+fn report_repository_error(name: &str, error: RepositoryError) {
+    match error {
+        RepositoryError::DoesNotExist => {
+            println!("ERROR: Repository {} does not exist", name);
+        }
+        RepositoryError::NotUTF8 => {
+            println!("ERROR: Repository {} configuration is not UTF8", name);
+        }
+        RepositoryError::IOError(e) => {
+            println!("ERROR: Internal IO error in repository {}: {}", name, e);
+        }
+        RepositoryError::InvalidPattern(e) => {
+            println!("ERROR: Invalid pattern in repository {}: {}", name, e);
+        }
+        RepositoryError::Git(e) => {
+            println!("ERROR: Git error in repository {}: {}", name, e);
+        }
+        RepositoryError::InvalidEncoding(e) => {
+            println!("ERROR: Unknown encoding {} in repository {}", e, name);
+        }
+        RepositoryError::UnknownLintRule(e) => {
+            println!("ERROR: Unknown lint rule {} configured for {}", e, name);
+        }
+    }
+}
+
+/// Finds the repository picked with `--repository`, or the first configured one.
+fn select_repository(config: &Settings, name: &Option<String>) -> Option<NamedRepository> {
+    let repositories = config.named_repositories();
+    match name {
+        Some(name) => {
+            let named = repositories.into_iter().find(|r| r.name == *name);
+            if named.is_none() {
+                println!("ERROR: Repository {} is not configured", name);
+            }
+            named
+        }
+        None => repositories.into_iter().next(),
+    }
+}
+
+/// Opens a configured repository along with the database its scripts run against,
+/// reporting what went wrong when either is not available.
+async fn open_workspace(
+    config: &Settings,
+    named: NamedRepository,
+    args: &ConnectionArgs,
+) -> eyre::Result<Option<(Repository, Workspace)>> {
+    let settings = config.for_repository(&named);
+    let Some(connection) = connect(args, &settings) else {
+        return Ok(None);
+    };
+    let repository = match open_repository(&settings) {
+        Ok(repository) => repository,
+        Err(e) => {
+            report_repository_error(&named.name, e);
+            return Ok(None);
+        }
+    };
+
+    let workspace = Workspace {
+        source: repository.source().clone(),
+        connection,
+        script_memory: ScriptDatabase::new(&named.name, settings.profile.as_deref()).await?,
+        encoding: repository.encoding(),
+        rules: repository.rules().clone(),
+        name: named.name,
+    };
+    Ok(Some((repository, workspace)))
+}
+
+/// Runs the interface, returning whether it could be started.
+async fn start_tui(
+    config: Settings,
+    args: &ConnectionArgs,
+    selected: Option<String>,
+    manifest: Option<PathBuf>,
+    report: Option<Report>,
+) -> eyre::Result<bool> {
+    let Some(selected) = select_repository(&config, &selected) else {
+        return Ok(false);
+    };
+    if !check_report(&report) {
+        return Ok(false);
+    }
+
+    let mut repositories = vec![];
+    let mut workspaces = vec![];
+    for named in config.named_repositories() {
+        let Some((repository, workspace)) = open_workspace(&config, named, args).await? else {
+            return Ok(false);
+        };
+        repository.build_index();
+        workspaces.push(workspace);
+        repositories.push(repository);
+    }
+    let current = workspaces
+        .iter()
+        .position(|w| w.name == selected.name)
+        .unwrap_or_default();
+
+    let queue = match manifest {
+        Some(ref manifest) => {
+            let named: Vec<(&str, &Repository)> = workspaces
+                .iter()
+                .map(|w| w.name.as_str())
+                .zip(repositories.iter())
+                .collect();
+            match Manifest::load(manifest).and_then(|m| m.resolve(&selected.name, &named)) {
+                Ok(queue) => queue,
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                    return Ok(false);
+                }
+            }
+        }
+        None => vec![],
     };
 
-    let repository = Repository::new(path.clone());
-    let script_memory = ScriptDatabase::new().await?;
+    initialize_logging()?;
+
+    initialize_panic_handler()?;
 
-    match repository {
-        Ok(repository) => {
-            let list = List::new(repository, path.clone(), script_memory.clone())?;
-            let script_status = ScriptStatus::new();
-            let scroll_list = ScrollList::new(connection.clone(), path, script_memory);
+    let orphans = Orphans::new(repositories.clone(), workspaces.clone());
+    let list = List::new(repositories, workspaces.clone(), current)?;
+    let script_status = ScriptStatus::new();
+    let scroll_list = ScrollList::new(
+        workspaces.clone(),
+        manifest.unwrap_or_else(|| PathBuf::from(DEFAULT_MANIFEST)),
+        report,
+    );
 
-            let mut app = App::new(
+    let mut app = App::new(
+        vec![
+            Screen::new(
+                Mode::FileChooser,
+                vec![Box::new(list), Box::new(Help::new())],
+            ),
+            Screen::new(
+                Mode::ScriptRunner,
+                vec![
+                    Box::new(scroll_list),
+                    Box::new(script_status),
+                    Box::new(Help::new()),
+                    Box::new(Confirm::new()),
+                ],
+            ),
+            Screen::new(
+                Mode::Orphans,
                 vec![
-                    Screen::new(
-                        Mode::FileChooser,
-                        vec![Box::new(list), Box::new(Help::new())],
-                    ),
-                    Screen::new(
-                        Mode::ScriptRunner,
-                        vec![
-                            Box::new(scroll_list),
-                            Box::new(script_status),
-                            Box::new(Help::new()),
-                        ],
-                    ),
+                    Box::new(orphans),
+                    Box::new(Help::new()),
+                    Box::new(Confirm::new()),
                 ],
-                config,
+            ),
+        ],
+        config,
+    );
+    if !queue.is_empty() {
+        app.state.load(&queue);
+    }
+
+    app.run().await?;
+    execute!(
+        stdout(),
+        Print("🦀 Thank you for using SquealMate 🦀\n".yellow())
+    )?;
+    Ok(true)
+}
+
+/// Marks or resets scripts given as paths relative to the repository root.
+/// Returns whether every script was known and recorded.
+async fn mark_scripts(
+    config: &Settings,
+    named: NamedRepository,
+    mut scripts: Vec<String>,
+    until: Option<String>,
+    reset: bool,
+) -> eyre::Result<bool> {
+    let config = config.for_repository(&named);
+    let repository = match open_repository(&config) {
+        Ok(repository) => repository,
+        Err(e) => {
+            report_repository_error(&named.name, e);
+            return Ok(false);
+        }
+    };
+    let script_memory = ScriptDatabase::new(&named.name, config.profile.as_deref()).await?;
+
+    if let Some(until) = until {
+        let preceding = repository.read_files_until(&until);
+        if preceding.is_empty() {
+            println!("ERROR: Script {} not found in repository", until);
+            return Ok(false);
+        }
+        scripts.extend(preceding);
+    }
+
+    let unknown: Vec<&String> = scripts
+        .iter()
+        .filter(|script| !repository.contains_script(script))
+        .collect();
+    if !unknown.is_empty() {
+        for script in unknown {
+            println!("ERROR: Script {} not found in repository", script);
+        }
+        return Ok(false);
+    }
+
+    let mut succeeded = true;
+    for script in scripts {
+        if reset {
+            script_memory.reset(&script)?;
+            println!("{} {}", "reset  ".yellow(), script);
+        } else {
+            match repository.source().read(&script) {
+                Ok(content) => {
+                    script_memory.mark_applied(script.clone(), checksum(&content))?;
+                    println!("{} {}", "applied".green(), script);
+                }
+                Err(e) => {
+                    println!("{} {} : {}", "error  ".red(), script, e);
+                    succeeded = false;
+                }
+            }
+        }
+    }
+
+    Ok(succeeded)
+}
+
+/// Selects scripts as given on the command line and queues them the same way as the explorer,
+/// with dependencies not applied yet added in front of them.
+fn queue_scripts(
+    repository: &Repository,
+    name: &str,
+    script_memory: &ScriptDatabase,
+    ordering: ScriptOrdering,
+    selection: &SelectionArgs,
+) -> Option<Vec<Script>> {
+    let mut errors = vec![];
+    let mut is_pending = |script: &str| {
+        script_memory
+            .is_pending(repository.source(), repository.rules(), script)
+            .unwrap_or_else(|e| {
+                errors.push(e);
+                false
+            })
+    };
+
+    let mut selected =
+        match runner::select(repository, &selection.scripts, selection.after.as_deref()) {
+            Ok(selected) => selected,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return None;
+            }
+        };
+    if let Some(since) = &selection.since {
+        match repository.changed_since(since, None) {
+            Ok(changed) => {
+                for script in changed {
+                    if !selected.contains(&script) {
+                        selected.push(script);
+                    }
+                }
+            }
+            Err(e) => {
+                report_repository_error(name, e);
+                return None;
+            }
+        }
+    }
+    if selection.pending {
+        for script in repository.get_children("".into()) {
+            if !selected.contains(&script) && is_pending(&script) {
+                selected.push(script);
+            }
+        }
+    }
+
+    let graph = repository.dependency_graph();
+    for problem in graph.problems() {
+        println!("{} {}", "warning".yellow(), problem);
+    }
+    let mut missing: Vec<String> = vec![];
+    for script in selected.iter() {
+        for requirement in graph.requirements(script) {
+            if !selected.contains(&requirement)
+                && !missing.contains(&requirement)
+                && is_pending(&requirement)
+            {
+                missing.push(requirement);
+            }
+        }
+    }
+    selected.extend(missing);
+
+    if !errors.is_empty() {
+        for e in errors {
+            println!("ERROR: {}", e);
+        }
+        return None;
+    }
+
+    let mut state = AppState::new(vec![QueueOrder {
+        repository: name.to_string(),
+        ordering,
+        rules: repository.rules().clone(),
+        dependencies: graph,
+    }]);
+    state.add_many(name, &selected);
+    Some(state.selected)
+}
+
+/// Prints the scripts that would run along with the batches sent for each of them.
+/// Returns whether every script could be prepared.
+async fn plan_scripts(
+    config: &Settings,
+    named: NamedRepository,
+    selection: &SelectionArgs,
+) -> eyre::Result<bool> {
+    let config = config.for_repository(&named);
+    let repository = match open_repository(&config) {
+        Ok(repository) => repository,
+        Err(e) => {
+            report_repository_error(&named.name, e);
+            return Ok(false);
+        }
+    };
+    let script_memory = ScriptDatabase::new(&named.name, config.profile.as_deref()).await?;
+
+    let Some(queue) = queue_scripts(
+        &repository,
+        &named.name,
+        &script_memory,
+        config.repository.ordering,
+        selection,
+    ) else {
+        return Ok(false);
+    };
+    if queue.is_empty() {
+        println!("Nothing to run");
+        return Ok(true);
+    }
+
+    let mut prepared_all = true;
+    for (number, script) in queue.iter().enumerate() {
+        let path = &script.relative_path;
+        let prepared = match runner::prepare(repository.source(), repository.encoding(), path) {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                println!("{} {} : {}", "error  ".red(), path, e);
+                prepared_all = false;
+                continue;
+            }
+        };
+        let status = script_memory
+            .status_of(repository.source(), path)
+            .map_or(State::Unknown, |status| State::from(&status));
+
+        println!(
+            "{} {} [{}] crc {:08x}, {}, {} batches",
+            format!("{:>3}.", number + 1).cyan(),
+            path,
+            status.label(),
+            prepared.crc,
+            prepared.encoding.name(),
+            prepared.batches.len()
+        );
+        for (batch_number, batch) in prepared.batches.iter().enumerate() {
+            println!(
+                "{}",
+                format!("---- batch {} ----", batch_number + 1).dark_grey()
+            );
+            println!("{}", batch.trim_end());
+        }
+        println!();
+    }
+    println!("{} scripts would run", queue.len());
+
+    Ok(prepared_all)
+}
+
+/// Checks the format of the report can be told, printing an error when it cannot.
+fn check_report(report: &Option<Report>) -> bool {
+    match report {
+        Some(report) if report.format().is_none() => {
+            println!(
+                "ERROR: Cannot tell the format of report {}, set it with --report-format",
+                report.path.display()
             );
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Runs the selected scripts one by one, printing progress as it goes.
+/// Returns whether every script ran successfully.
+async fn run_scripts(
+    config: &Settings,
+    named: NamedRepository,
+    args: &ConnectionArgs,
+    run: RunArgs,
+) -> eyre::Result<bool> {
+    let report = run.report.merge(config);
+    if !check_report(&report) {
+        return Ok(false);
+    }
+    let ordering = config.for_repository(&named).repository.ordering;
+    let Some((repository, workspace)) = open_workspace(config, named, args).await? else {
+        return Ok(false);
+    };
+
+    let Some(mut queue) = queue_scripts(
+        &repository,
+        &workspace.name,
+        &workspace.script_memory,
+        ordering,
+        &run.selection,
+    ) else {
+        return Ok(false);
+    };
+    if queue.is_empty() {
+        println!("Nothing to run");
+        return Ok(true);
+    }
+
+    let (mut applied, mut failed) = (0, 0);
+    for script in queue.iter_mut() {
+        let path = script.relative_path.clone();
+        println!("{} {}", "running".cyan(), path);
+
+        let execution = runner::execute(&workspace, &path).await;
+        script.elapsed = Some(execution.elapsed);
+        match execution.result {
+            Ok(output) => {
+                if let Some(crc) = execution.crc {
+                    workspace.script_memory.insert(path.clone(), crc, true)?;
+                }
+                println!("{} {} ({} ms)", "applied".green(), path, execution.elapsed);
+                for line in output.iter() {
+                    println!("        {}", line);
+                }
+                script.state = ScriptState::Finished;
+                script.output = output;
+                applied += 1;
+            }
+            Err(e) => {
+                if let Some(crc) = execution.crc {
+                    workspace.script_memory.insert(path.clone(), crc, false)?;
+                }
+                println!("{} {} : {}", "error  ".red(), path, e);
+                script.state = ScriptState::Error;
+                script.error = Some(e);
+                failed += 1;
+                if !run.skip_errors {
+                    break;
+                }
+            }
+        }
+    }
+
+    let skipped = queue.len() - applied - failed;
+    println!(
+        "{} applied, {} failed, {} not run",
+        applied, failed, skipped
+    );
+
+    if let Some(report) = report {
+        match report.write(&queue) {
+            Ok(()) => println!("Report written to {}", report.path.display()),
+            Err(e) => println!(
+                "ERROR: Cannot write report {}: {}",
+                report.path.display(),
+                e
+            ),
+        }
+    }
+    Ok(failed == 0)
+}
+
+/// Prints the status of every script of the repository.
+/// Returns whether any script still has to run.
+async fn report_status(
+    config: &Settings,
+    named: NamedRepository,
+    all: bool,
+    format: OutputFormat,
+) -> eyre::Result<Option<bool>> {
+    let config = config.for_repository(&named);
+    let repository = match open_repository(&config) {
+        Ok(repository) => repository,
+        Err(e) => {
+            report_repository_error(&named.name, e);
+            return Ok(None);
+        }
+    };
+    let script_memory = ScriptDatabase::new(&named.name, config.profile.as_deref()).await?;
+
+    let mut statuses = vec![];
+    for script in repository.get_children("".into()) {
+        let status = script_memory
+            .status_of(repository.source(), &script)
+            .unwrap_or_else(|e| {
+                log::error!("Cannot get status of {} : {}", script, e);
+                EntryStatus::Unknown
+            });
+        statuses.push((script, status));
+    }
+
+    let report = StatusReport::new(&named.name, statuses, all);
+    match format {
+        OutputFormat::Table => print!("{}", report.to_table()),
+        OutputFormat::Json => println!("{}", report.to_json()),
+    }
+    Ok(Some(report.has_pending()))
+}
+
+/// Lists orphaned history, pruning or archiving it when asked to.
+/// Returns whether the repository could be checked.
+async fn report_orphans(
+    config: &Settings,
+    named: NamedRepository,
+    prune: bool,
+    archive: bool,
+) -> eyre::Result<bool> {
+    let repository = match open_repository(&config.for_repository(&named)) {
+        Ok(repository) => repository,
+        Err(e) => {
+            report_repository_error(&named.name, e);
+            return Ok(false);
+        }
+    };
+    let script_memory = ScriptDatabase::new(&named.name, config.profile.as_deref()).await?;
 
-            app.run().await?;
-            execute!(
-                stdout(),
-                Print("🦀 Thank you for using SquealMate 🦀\n".yellow())
-            )?;
-            Ok(())
+    let orphans =
+        script_memory.find_orphans(repository.source(), &repository.get_children("".into()))?;
+
+    if orphans.is_empty() {
+        println!("No orphaned history found");
+        return Ok(true);
+    }
+
+    for orphan in orphans.iter() {
+        println!("{}", orphan);
+    }
+
+    if prune {
+        script_memory.prune(&orphans)?;
+        println!("{} {} records", "Pruned".yellow(), orphans.len());
+    } else if archive {
+        script_memory.archive(&orphans)?;
+        println!("{} {} records", "Archived".yellow(), orphans.len());
+    } else {
+        println!("{} orphaned records", orphans.len());
+    }
+
+    Ok(true)
+}
+
+/// Prints the scripts changed between two refs. Returns whether they could be listed.
+fn list_changed(
+    config: &Settings,
+    named: NamedRepository,
+    since: Option<String>,
+    until: Option<String>,
+) -> bool {
+    let config = config.for_repository(&named);
+    let repository = match open_repository(&config) {
+        Ok(repository) => repository,
+        Err(e) => {
+            report_repository_error(&named.name, e);
+            return false;
+        }
+    };
+    let since = since.unwrap_or_else(|| repository.base_ref().to_string());
+
+    match repository.changed_since(&since, until.as_deref()) {
+        Ok(scripts) => {
+            for script in scripts {
+                println!("{}", script);
+            }
+            true
+        }
+        Err(e) => {
+            report_repository_error(&named.name, e);
+            false
         }
-        Err(RepositoryError::DoesNotExist) => {
-            log::error!("Repository does not exist");
-            Ok(())
+    }
+}
+
+/// Creates the next script in `dir`, or next to the last script of the repository,
+/// and opens it in the editor. Returns whether the script was created.
+fn new_script(
+    config: &Settings,
+    named: NamedRepository,
+    description: &str,
+    dir: Option<String>,
+    ticket: Option<String>,
+    edit: bool,
+) -> bool {
+    let settings = config.for_repository(&named);
+    let repository = match open_repository(&settings) {
+        Ok(repository) => repository,
+        Err(e) => {
+            report_repository_error(&named.name, e);
+            return false;
+        }
+    };
+    let directory = dir.unwrap_or_else(|| {
+        repository
+            .get_children("".into())
+            .last()
+            .and_then(|last| Path::new(last).parent())
+            .and_then(|parent| parent.to_str())
+            .unwrap_or_default()
+            .to_string()
+    });
+
+    let path = match scaffold::create(
+        &repository,
+        &directory,
+        description,
+        &ticket.unwrap_or_default(),
+        config.scaffold.as_ref(),
+    ) {
+        Ok((relative, path)) => {
+            println!("{} {}", "created".green(), relative);
+            path
         }
-        Err(RepositoryError::NotUTF8) => {
-            log::error!("Repository configuration is not UTF8");
-            Ok(())
+        Err(e) => {
+            println!("ERROR: {}", e);
+            return false;
         }
-        Err(RepositoryError::IOError(e)) => {
-            log::error!("Internal IO error: {}", e);
-            Ok(())
+    };
+
+    if edit {
+        if let Err(e) = scaffold::open_in_editor(&path) {
+            println!("ERROR: {}", e);
+            return false;
         }
     }
+    true
 }
 
-fn draw_config(stdout: &mut io::Stdout) -> eyre::Result<()> {
+/// Prints the findings of the linter for the given scripts, or every script of the repository.
+/// Returns whether no script failed a rule set to error, or any rule with `deny_warnings`.
+fn lint_scripts(
+    config: &Settings,
+    named: NamedRepository,
+    scripts: &[String],
+    deny_warnings: bool,
+) -> bool {
+    let settings = config.for_repository(&named);
+    let repository = match open_repository(&settings) {
+        Ok(repository) => repository,
+        Err(e) => {
+            report_repository_error(&named.name, e);
+            return false;
+        }
+    };
+    let scripts = if scripts.is_empty() {
+        repository.get_children("".into())
+    } else {
+        match runner::select(&repository, scripts, None) {
+            Ok(selected) => selected,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                return false;
+            }
+        }
+    };
+
+    let (mut errors, mut warnings, mut failed) = (0, 0, 0);
+    for script in scripts.iter() {
+        let findings = match repository.read_script(script) {
+            Ok(decoded) => repository.linter().lint(&decoded.content),
+            Err(e) => {
+                println!("{} {} : {}", "error  ".red(), script, e);
+                errors += 1;
+                continue;
+            }
+        };
+        if !findings.is_empty() {
+            failed += 1;
+        }
+        for finding in findings {
+            let severity = match finding.severity {
+                Severity::Error => {
+                    errors += 1;
+                    "error  ".red()
+                }
+                _ => {
+                    warnings += 1;
+                    "warning".yellow()
+                }
+            };
+            println!(
+                "{} {}:{} {} [{}]",
+                severity,
+                script,
+                finding.line,
+                finding.message,
+                finding.rule.name()
+            );
+        }
+    }
+
+    println!(
+        "{} errors, {} warnings in {} of {} scripts",
+        errors,
+        warnings,
+        failed,
+        scripts.len()
+    );
+    errors == 0 && (!deny_warnings || warnings == 0)
+}
+
+fn draw_config(stdout: &mut io::Stdout, config: &Settings) -> eyre::Result<()> {
     let config_path = get_config_dir();
     let data_path = get_data_dir();
     let config_path_str = config_path.to_str().expect("Unknown host system").white();
@@ -107,6 +849,27 @@ fn draw_config(stdout: &mut io::Stdout) -> eyre::Result<()> {
     let version_msg = format!("Version: {}\n", version);
     let config_msg = format!("Config src: {}\n", config_path_str);
     let data_msg = format!("Logs dir: {}\n", data_path_str);
+    let project_msg = format!(
+        "Project config: {}\n",
+        config
+            .project
+            .as_ref()
+            .map_or("none".to_string(), |p| format!(
+                "{} (repository paths are taken from it)",
+                p.display()
+            ))
+            .white()
+    );
+    let profiles: Vec<&str> = config.profiles.keys().map(|p| p.as_str()).collect();
+    let profile_msg = format!(
+        "Profile: {} (configured: {})\n",
+        config.profile.as_deref().unwrap_or("none").white(),
+        if profiles.is_empty() {
+            "none".to_string()
+        } else {
+            profiles.join(", ")
+        }
+    );
     execute!(
         stdout,
         Print("🦀 SquealMate 🦀\n".yellow()),
@@ -115,7 +878,9 @@ fn draw_config(stdout: &mut io::Stdout) -> eyre::Result<()> {
         Print("Edition: "),
         Print("Ultimate\n\n".white()),
         Print(config_msg),
-        Print(data_msg)
+        Print(data_msg),
+        Print(project_msg),
+        Print(profile_msg)
     )?;
 
     stdout.flush()?;
@@ -181,23 +946,64 @@ fn init_config() -> eyre::Result<()> {
             .interact()?;
         settings.database.username = Some(username);
 
-        let store_password: bool = confirm(
-            "Do you want to store the password in the configuration file? (Not recommended)",
-        )
-        .initial_value(false)
-        .interact()?;
+        let source = select("How should the password be provided?")
+            .item(
+                PasswordSource::Prompt,
+                "Ask for it at startup",
+                "typed in, never stored",
+            )
+            .item(
+                PasswordSource::Environment,
+                "Environment variable",
+                "read from a variable you set",
+            )
+            .item(
+                PasswordSource::Command,
+                "Command",
+                "printed by pass, op, a vault CLI...",
+            )
+            .item(
+                PasswordSource::Stored,
+                "Store in the configuration file",
+                "plain text, not recommended",
+            )
+            .initial_value(PasswordSource::Prompt)
+            .interact()?;
 
-        if store_password {
-            let password: String = input("SQL user password")
-                .validate(|input: &String| {
-                    if input.is_empty() {
-                        Err("Password cannot be empty")
-                    } else {
-                        Ok(())
-                    }
-                })
-                .interact()?;
-            settings.database.password = Some(password);
+        match source {
+            PasswordSource::Prompt => {}
+            PasswordSource::Environment => {
+                let name: String = input("Environment variable with the password")
+                    .default_input("SQUEALMATE_PASSWORD")
+                    .interact()?;
+                settings.database.password_env = Some(name);
+            }
+            PasswordSource::Command => {
+                let command: String = input("Command printing the password")
+                    .placeholder("pass show database/dev")
+                    .validate(|input: &String| {
+                        if input.is_empty() {
+                            Err("Command cannot be empty")
+                        } else {
+                            Ok(())
+                        }
+                    })
+                    .interact()?;
+                settings.database.password_command = Some(command);
+            }
+            PasswordSource::Stored => {
+                let password: String = password("SQL user password")
+                    .mask('▪')
+                    .validate(|input: &String| {
+                        if input.is_empty() {
+                            Err("Password cannot be empty")
+                        } else {
+                            Ok(())
+                        }
+                    })
+                    .interact()?;
+                settings.database.password = Some(password);
+            }
         }
     }
 
@@ -223,9 +1029,23 @@ fn init_config() -> eyre::Result<()> {
             if let Some(ref username) = settings.database.username {
                 cliclack::log::info(format!("SQl user name: {}", username))?;
             }
-            if let Some(ref password) = settings.database.password {
-                cliclack::log::info(format!("SQl user password: {}", password))?;
-            }
+            let source = match PasswordSource::of(&settings.database) {
+                PasswordSource::Stored => "stored in the configuration file".to_string(),
+                PasswordSource::Environment => format!(
+                    "read from ${}",
+                    settings.database.password_env.clone().unwrap_or_default()
+                ),
+                PasswordSource::Command => format!(
+                    "printed by `{}`",
+                    settings
+                        .database
+                        .password_command
+                        .clone()
+                        .unwrap_or_default()
+                ),
+                PasswordSource::Prompt => "asked for at startup".to_string(),
+            };
+            cliclack::log::info(format!("SQl user password: {}", source))?;
         }
     }
 
@@ -275,32 +1095,143 @@ fn init_config() -> eyre::Result<()> {
 async fn main() -> eyre::Result<()> {
     let mut stdout = io::stdout();
 
-    let config = Settings::new().expect("Error while loading config!");
+    let config = match Settings::new() {
+        Ok(config) => config,
+        Err(e) => {
+            println!("ERROR: {}", e);
+            std::process::exit(libc::EXIT_FAILURE);
+        }
+    };
 
     let args = SquealMateArgs::parse();
 
+    let config = match config.with_profile(args.profile.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("ERROR: {}", e);
+            std::process::exit(libc::EXIT_FAILURE);
+        }
+    };
+
     match args.command {
         Some(Command::Config) => {
-            draw_config(&mut stdout)?;
+            draw_config(&mut stdout, &config)?;
         }
-        Some(Command::Migrations) | None => {
-            match args.connection.merge(&config) {
-                Ok(conn) => start_tui(config, conn).await?,
-                Err(ArgumentsError::MissingPassword) => {
-                    println!("ERROR: Missing DB password");
-                }
-                Err(ArgumentsError::MissingUsername) => {
-                    println!("ERROR: Missing DB username");
-                }
-                Err(ArgumentsError::MissingDBName) => {
-                    println!("ERROR: Missing DB name");
-                }
-                Err(ArgumentsError::PortNotNumber) => {
-                    println!("ERROR: Supplied port is not a valid number");
+        Some(Command::Migrations { manifest, report }) => {
+            let report = report.merge(&config);
+            if !start_tui(config, &args.connection, args.repository, manifest, report).await? {
+                stdout.flush()?;
+                std::process::exit(libc::EXIT_FAILURE);
+            }
+        }
+        None => {
+            let report = config.report.clone();
+            if !start_tui(config, &args.connection, args.repository, None, report).await? {
+                stdout.flush()?;
+                std::process::exit(libc::EXIT_FAILURE);
+            }
+        }
+        Some(Command::Initialize) => init_config()?,
+        Some(Command::Mark {
+            scripts,
+            until,
+            reset,
+        }) => {
+            let Some(named) = select_repository(&config, &args.repository) else {
+                std::process::exit(libc::EXIT_FAILURE);
+            };
+            if !mark_scripts(&config, named, scripts, until, reset).await? {
+                stdout.flush()?;
+                std::process::exit(libc::EXIT_FAILURE);
+            }
+        }
+        Some(Command::Run(run)) => {
+            let Some(named) = select_repository(&config, &args.repository) else {
+                std::process::exit(libc::EXIT_FAILURE);
+            };
+            let succeeded = if run.dry_run {
+                plan_scripts(&config, named, &run.selection).await?
+            } else {
+                run_scripts(&config, named, &args.connection, run).await?
+            };
+            if !succeeded {
+                stdout.flush()?;
+                std::process::exit(libc::EXIT_FAILURE);
+            }
+        }
+        Some(Command::Plan(selection)) => {
+            let Some(named) = select_repository(&config, &args.repository) else {
+                std::process::exit(libc::EXIT_FAILURE);
+            };
+            if !plan_scripts(&config, named, &selection).await? {
+                stdout.flush()?;
+                std::process::exit(libc::EXIT_FAILURE);
+            }
+        }
+        Some(Command::Status { all, format }) => {
+            let Some(named) = select_repository(&config, &args.repository) else {
+                std::process::exit(libc::EXIT_FAILURE);
+            };
+            match report_status(&config, named, all, format).await? {
+                Some(false) => {}
+                Some(true) => {
+                    stdout.flush()?;
+                    std::process::exit(PENDING_EXIT_CODE);
                 }
+                None => std::process::exit(libc::EXIT_FAILURE),
+            }
+        }
+        Some(Command::New {
+            description,
+            dir,
+            ticket,
+            no_edit,
+        }) => {
+            let Some(named) = select_repository(&config, &args.repository) else {
+                std::process::exit(libc::EXIT_FAILURE);
             };
+            if !new_script(
+                &config,
+                named,
+                &description.join(" "),
+                dir,
+                ticket,
+                !no_edit,
+            ) {
+                stdout.flush()?;
+                std::process::exit(libc::EXIT_FAILURE);
+            }
+        }
+        Some(Command::Lint {
+            scripts,
+            deny_warnings,
+        }) => {
+            let Some(named) = select_repository(&config, &args.repository) else {
+                std::process::exit(libc::EXIT_FAILURE);
+            };
+            if !lint_scripts(&config, named, &scripts, deny_warnings) {
+                stdout.flush()?;
+                std::process::exit(libc::EXIT_FAILURE);
+            }
+        }
+        Some(Command::Changed { since, until }) => {
+            let Some(named) = select_repository(&config, &args.repository) else {
+                std::process::exit(libc::EXIT_FAILURE);
+            };
+            if !list_changed(&config, named, since, until) {
+                stdout.flush()?;
+                std::process::exit(libc::EXIT_FAILURE);
+            }
+        }
+        Some(Command::Orphans { prune, archive }) => {
+            let Some(named) = select_repository(&config, &args.repository) else {
+                std::process::exit(libc::EXIT_FAILURE);
+            };
+            if !report_orphans(&config, named, prune, archive).await? {
+                stdout.flush()?;
+                std::process::exit(libc::EXIT_FAILURE);
+            }
         }
-        Some(Command::Initialize) => init_config()?,
     }
 
     Ok(())
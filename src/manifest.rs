@@ -0,0 +1,251 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    repository::Repository,
+    source::{native_path, portable_path},
+};
+
+#[derive(Debug, PartialEq)]
+pub enum ManifestError {
+    IOError(String),
+    Parse(String),
+    UnknownRepository(String),
+    MissingScript { repository: String, path: String },
+}
+
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::IOError(e) => write!(f, "Cannot access manifest: {}", e),
+            ManifestError::Parse(e) => write!(f, "Invalid manifest: {}", e),
+            ManifestError::UnknownRepository(name) => {
+                write!(f, "Repository {} is not configured", name)
+            }
+            ManifestError::MissingScript { repository, path } => {
+                write!(f, "Script {} not found in repository {}", path, repository)
+            }
+        }
+    }
+}
+
+/// Script listed in a manifest, the repository is left out when there is only one.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ManifestEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    pub path: String,
+}
+
+/// List of scripts to run, in the order they have to run.
+///
+/// Manifests ending with `.toml` list scripts as `[[scripts]]` tables with a `path`
+/// and an optional `repository`. Any other file is read as text with one script per line,
+/// optionally prefixed by its repository as `repository:path`. Empty lines and lines
+/// starting with `#` are skipped. Paths may be separated by `/` or `\` whatever the platform.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
+pub struct Manifest {
+    #[serde(default)]
+    pub scripts: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let content =
+            fs::read_to_string(path).map_err(|e| ManifestError::IOError(e.to_string()))?;
+
+        let mut manifest = if is_toml(path) {
+            toml::from_str(&content).map_err(|e| ManifestError::Parse(e.to_string()))?
+        } else {
+            Self::parse_text(&content)
+        };
+
+        for entry in manifest.scripts.iter_mut() {
+            entry.path = native_path(&entry.path);
+        }
+        Ok(manifest)
+    }
+
+    /// Writes the manifest with paths separated by `/`, so it can be used on any platform.
+    pub fn save(&self, path: &Path) -> Result<(), ManifestError> {
+        let mut portable = self.clone();
+        for entry in portable.scripts.iter_mut() {
+            entry.path = portable_path(&entry.path);
+        }
+
+        let content = if is_toml(path) {
+            toml::to_string(&portable).map_err(|e| ManifestError::Parse(e.to_string()))?
+        } else {
+            portable.to_text()
+        };
+
+        fs::write(path, content).map_err(|e| ManifestError::IOError(e.to_string()))
+    }
+
+    fn parse_text(content: &str) -> Self {
+        let scripts = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.split_once(':') {
+                Some((repository, path)) => ManifestEntry {
+                    repository: Some(repository.trim().to_string()),
+                    path: path.trim().to_string(),
+                },
+                None => ManifestEntry {
+                    repository: None,
+                    path: line.to_string(),
+                },
+            })
+            .collect();
+
+        Self { scripts }
+    }
+
+    fn to_text(&self) -> String {
+        self.scripts
+            .iter()
+            .map(|entry| match entry.repository {
+                Some(ref repository) => format!("{}:{}\n", repository, entry.path),
+                None => format!("{}\n", entry.path),
+            })
+            .collect()
+    }
+
+    /// Checks every script exists in its repository, returning the scripts as
+    /// repository names and paths. Scripts without a repository belong to `default`.
+    pub fn resolve(
+        &self,
+        default: &str,
+        repositories: &[(&str, &Repository)],
+    ) -> Result<Vec<(String, String)>, ManifestError> {
+        self.scripts
+            .iter()
+            .map(|entry| {
+                let name = entry.repository.as_deref().unwrap_or(default);
+                let (_, repository) = repositories
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .ok_or_else(|| ManifestError::UnknownRepository(name.to_string()))?;
+
+                if repository.contains_script(&entry.path) {
+                    Ok((name.to_string(), entry.path.clone()))
+                } else {
+                    Err(ManifestError::MissingScript {
+                        repository: name.to_string(),
+                        path: entry.path.clone(),
+                    })
+                }
+            })
+            .collect()
+    }
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn manifest_text() {
+        let manifest = Manifest::load(Path::new(".tests/manifest/release.txt")).unwrap();
+        assert_eq!(
+            vec![
+                ManifestEntry {
+                    repository: None,
+                    path: "sub/005.sql".into()
+                },
+                ManifestEntry {
+                    repository: Some("core".into()),
+                    path: "001.sql".into()
+                },
+            ],
+            manifest.scripts
+        );
+        assert_eq!("sub/005.sql\ncore:001.sql\n", manifest.to_text());
+    }
+
+    #[test]
+    fn manifest_toml() {
+        let manifest = Manifest::load(Path::new(".tests/manifest/release.toml")).unwrap();
+        assert_eq!(2, manifest.scripts.len());
+        assert_eq!(Some("core".into()), manifest.scripts[1].repository);
+
+        let saved: Manifest = toml::from_str(&toml::to_string(&manifest).unwrap()).unwrap();
+        assert_eq!(manifest, saved);
+    }
+
+    #[test]
+    fn manifest_separators() {
+        let root = std::env::temp_dir().join(format!("squealmate-manifest-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let text = root.join("release.txt");
+        fs::write(&text, "sub\\005.sql\ncore:sub/006.sql\n").unwrap();
+
+        let manifest = Manifest::load(&text).unwrap();
+        let native: Vec<PathBuf> = manifest
+            .scripts
+            .iter()
+            .map(|entry| PathBuf::from(&entry.path))
+            .collect();
+        assert_eq!(
+            vec![
+                Path::new("sub").join("005.sql"),
+                Path::new("sub").join("006.sql")
+            ],
+            native
+        );
+
+        manifest.save(&text).unwrap();
+        assert_eq!(
+            "sub/005.sql\ncore:sub/006.sql\n",
+            fs::read_to_string(&text).unwrap()
+        );
+
+        let toml = root.join("release.toml");
+        manifest.save(&toml).unwrap();
+        assert!(fs::read_to_string(&toml)
+            .unwrap()
+            .contains("path = \"sub/005.sql\""));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn manifest_resolve() {
+        let repository = Repository::new(PathBuf::from(".tests/repository/rules")).unwrap();
+        let repositories = [("default", &repository), ("core", &repository)];
+
+        let manifest = Manifest::load(Path::new(".tests/manifest/release.toml")).unwrap();
+        assert_eq!(
+            vec![
+                ("default".to_string(), "sub/005.sql".to_string()),
+                ("core".to_string(), "001.sql".to_string())
+            ],
+            manifest.resolve("default", &repositories).unwrap()
+        );
+
+        let missing = Manifest::parse_text("sub/004.tsql\n");
+        assert_eq!(
+            Err(ManifestError::MissingScript {
+                repository: "default".into(),
+                path: "sub/004.tsql".into()
+            }),
+            missing.resolve("default", &repositories)
+        );
+
+        let unknown = Manifest::parse_text("reports:001.sql\n");
+        assert_eq!(
+            Err(ManifestError::UnknownRepository("reports".into())),
+            unknown.resolve("default", &repositories)
+        );
+    }
+}
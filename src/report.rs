@@ -0,0 +1,338 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::{Script, ScriptState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Junit,
+    Json,
+    Markdown,
+}
+
+/// File the results of a run are written to, configured as `[report]` or given by flags.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Report {
+    pub path: PathBuf,
+    /// Picked from the extension of the path when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<ReportFormat>,
+}
+
+impl Report {
+    /// The configured format, or the one matching the extension of the path.
+    pub fn format(&self) -> Option<ReportFormat> {
+        self.format.or_else(|| {
+            let extension = self.path.extension()?.to_str()?.to_lowercase();
+            match extension.as_str() {
+                "xml" => Some(ReportFormat::Junit),
+                "json" => Some(ReportFormat::Json),
+                "md" | "markdown" => Some(ReportFormat::Markdown),
+                _ => None,
+            }
+        })
+    }
+
+    /// Writes the results of the `scripts` of a run, scripts that did not run are reported as skipped.
+    pub fn write(&self, scripts: &[Script]) -> std::io::Result<()> {
+        let content = match self.format() {
+            Some(ReportFormat::Junit) => junit(scripts),
+            Some(ReportFormat::Json) => json(scripts),
+            Some(ReportFormat::Markdown) => markdown(scripts),
+            None => {
+                return Err(std::io::Error::other(format!(
+                    "Unknown report format of {}",
+                    self.path.display()
+                )))
+            }
+        };
+
+        fs::write(&self.path, content)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Passed,
+    RolledBack,
+    Failed,
+    Skipped,
+}
+
+impl Outcome {
+    fn of(script: &Script) -> Self {
+        match script.state {
+            ScriptState::Finished => Outcome::Passed,
+            ScriptState::RolledBack => Outcome::RolledBack,
+            ScriptState::Error => Outcome::Failed,
+            ScriptState::Running | ScriptState::None => Outcome::Skipped,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ScriptResult<'a> {
+    repository: &'a str,
+    script: &'a str,
+    outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    output: &'a [String],
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Totals {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    elapsed_ms: u128,
+}
+
+fn totals<'a>(scripts: impl Iterator<Item = &'a Script>) -> Totals {
+    let mut totals = Totals::default();
+    for script in scripts {
+        match Outcome::of(script) {
+            Outcome::Passed | Outcome::RolledBack => totals.passed += 1,
+            Outcome::Failed => totals.failed += 1,
+            Outcome::Skipped => totals.skipped += 1,
+        }
+        totals.elapsed_ms += script.elapsed.unwrap_or_default();
+    }
+    totals
+}
+
+fn json(scripts: &[Script]) -> String {
+    #[derive(Serialize)]
+    struct JsonReport<'a> {
+        totals: Totals,
+        scripts: Vec<ScriptResult<'a>>,
+    }
+
+    let report = JsonReport {
+        totals: totals(scripts.iter()),
+        scripts: scripts
+            .iter()
+            .map(|script| ScriptResult {
+                repository: &script.repository,
+                script: &script.relative_path,
+                outcome: Outcome::of(script),
+                elapsed_ms: script.elapsed,
+                error: script.error.as_deref(),
+                output: &script.output,
+            })
+            .collect(),
+    };
+    serde_json::to_string_pretty(&report).expect("Run report is serializable")
+}
+
+/// One test suite per repository, one test case per script.
+fn junit(scripts: &[Script]) -> String {
+    let seconds = |ms: u128| format!("{:.3}", ms as f64 / 1000.0);
+
+    let mut repositories: Vec<&str> = vec![];
+    for script in scripts {
+        if !repositories.contains(&script.repository.as_str()) {
+            repositories.push(&script.repository);
+        }
+    }
+
+    let all = totals(scripts.iter());
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"squealmate\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+        scripts.len(),
+        all.failed,
+        all.skipped,
+        seconds(all.elapsed_ms)
+    ));
+
+    for repository in repositories {
+        let suite: Vec<&Script> = scripts
+            .iter()
+            .filter(|s| s.repository == repository)
+            .collect();
+        let suite_totals = totals(suite.iter().copied());
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+            escape_xml(repository),
+            suite.len(),
+            suite_totals.failed,
+            suite_totals.skipped,
+            seconds(suite_totals.elapsed_ms)
+        ));
+
+        for script in suite {
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+                escape_xml(&script.relative_path),
+                escape_xml(repository),
+                seconds(script.elapsed.unwrap_or_default())
+            ));
+
+            let outcome = Outcome::of(script);
+            if outcome == Outcome::Passed && script.output.is_empty() {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(">\n");
+
+            match outcome {
+                Outcome::Failed => {
+                    let error = escape_xml(script.error.as_deref().unwrap_or_default());
+                    xml.push_str(&format!(
+                        "      <failure message=\"{}\">{}</failure>\n",
+                        error, error
+                    ));
+                }
+                Outcome::Skipped => xml.push_str("      <skipped/>\n"),
+                Outcome::RolledBack | Outcome::Passed => {}
+            }
+            // A test case takes a single system-out, the rollback is noted in front of the output
+            let rolled_back = (outcome == Outcome::RolledBack).then_some("Rolled back");
+            let output: Vec<&str> = rolled_back
+                .into_iter()
+                .chain(script.output.iter().map(|line| line.as_str()))
+                .collect();
+            if !output.is_empty() {
+                xml.push_str(&format!(
+                    "      <system-out>{}</system-out>\n",
+                    escape_xml(&output.join("\n"))
+                ));
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn markdown(scripts: &[Script]) -> String {
+    let all = totals(scripts.iter());
+    let mut md = format!(
+        "# SquealMate run\n\n{} passed, {} failed, {} not run in {} ms\n\n",
+        all.passed, all.failed, all.skipped, all.elapsed_ms
+    );
+    md.push_str("| Repository | Script | Result | Duration | Details |\n");
+    md.push_str("|---|---|---|---|---|\n");
+
+    for script in scripts {
+        let result = match Outcome::of(script) {
+            Outcome::Passed => "passed",
+            Outcome::RolledBack => "rolled back",
+            Outcome::Failed => "**failed**",
+            Outcome::Skipped => "not run",
+        };
+        let details = script
+            .error
+            .iter()
+            .chain(script.output.iter())
+            .map(|line| escape_markdown(line))
+            .collect::<Vec<_>>()
+            .join("<br>");
+
+        md.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            escape_markdown(&script.repository),
+            escape_markdown(&script.relative_path),
+            result,
+            script
+                .elapsed
+                .map_or(String::new(), |ms| format!("{} ms", ms)),
+            details
+        ));
+    }
+    md
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn scripts() -> Vec<Script> {
+        let mut applied = Script::finished("core", "001.sql", 1500);
+        applied.output = vec!["Batch 1: 1 result sets, 3 rows".into()];
+        vec![
+            applied,
+            Script::error("core", "002.sql", "Invalid object name 'a<b'".into()),
+            Script::none("reports", "001.sql"),
+        ]
+    }
+
+    #[test]
+    fn report_format() {
+        let report = |path: &str, format| Report {
+            path: PathBuf::from(path),
+            format,
+        };
+        assert_eq!(Some(ReportFormat::Junit), report("run.xml", None).format());
+        assert_eq!(
+            Some(ReportFormat::Markdown),
+            report("run.MD", None).format()
+        );
+        assert_eq!(
+            Some(ReportFormat::Json),
+            report("run.txt", Some(ReportFormat::Json)).format()
+        );
+        assert_eq!(None, report("run.txt", None).format());
+        assert_eq!(
+            Some(ReportFormat::Junit),
+            report("results", Some(ReportFormat::Junit)).format()
+        );
+        assert_eq!(None, report("results", None).format());
+    }
+
+    #[test]
+    fn report_junit() {
+        let xml = junit(&scripts());
+        assert!(xml.contains(
+            "<testsuites name=\"squealmate\" tests=\"3\" failures=\"1\" skipped=\"1\" time=\"1.500\">"
+        ));
+        assert!(xml.contains("<testsuite name=\"core\" tests=\"2\" failures=\"1\" skipped=\"0\""));
+        assert!(xml.contains("<system-out>Batch 1: 1 result sets, 3 rows</system-out>"));
+        assert!(xml.contains("<failure message=\"Invalid object name &apos;a&lt;b&apos;\">"));
+        assert!(xml.contains(
+            "<testcase name=\"001.sql\" classname=\"reports\" time=\"0.000\">\n      <skipped/>"
+        ));
+
+        let mut rolled_back = Script::finished("core", "003.sql", 200);
+        rolled_back.state = ScriptState::RolledBack;
+        rolled_back.output = vec!["Batch 1: 2 rows affected".into()];
+        let xml = junit(&[rolled_back]);
+        assert_eq!(1, xml.matches("<system-out>").count());
+        assert!(xml.contains("<system-out>Rolled back\nBatch 1: 2 rows affected</system-out>"));
+    }
+
+    #[test]
+    fn report_json_and_markdown() {
+        let report: serde_json::Value = serde_json::from_str(&json(&scripts())).unwrap();
+        assert_eq!(1, report["totals"]["failed"]);
+        assert_eq!("passed", report["scripts"][0]["outcome"]);
+        assert_eq!(1500, report["scripts"][0]["elapsed_ms"]);
+        assert_eq!("skipped", report["scripts"][2]["outcome"]);
+        assert!(report["scripts"][2].get("error").is_none());
+
+        let md = markdown(&scripts());
+        assert!(md.contains("1 passed, 1 failed, 1 not run in 1500 ms"));
+        assert!(md.contains("| core | 002.sql | **failed** |  | Invalid object name 'a<b' |"));
+    }
+}
@@ -1,29 +1,57 @@
 use std::{
-    fs::{self, read_dir},
-    path::PathBuf,
+    cmp::Ordering,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
 };
 
 use color_eyre::eyre;
-use walkdir::{DirEntry, WalkDir};
-
-use crate::entries::{EntryStatus, ListEntry};
+use encoding_rs::Encoding;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compression, config,
+    dependencies::{parse_dependencies, DependencyGraph},
+    encoding::{self, Decoded},
+    entries::{EntryStatus, ListEntry},
+    git::{self, GitStatus},
+    index::{IndexEntry, RepositoryIndex, ScriptInfo},
+    lint::Linter,
+    source::ScriptSource,
+};
 
 #[derive(Debug)]
 pub enum RepositoryError {
     DoesNotExist,
     IOError(String),
     NotUTF8,
+    InvalidPattern(String),
+    Git(String),
+    InvalidEncoding(String),
+    UnknownLintRule(String),
 }
 
+#[derive(Clone)]
 pub struct Repository {
     root: PathBuf,
     root_str: String,
     path: Vec<String>,
+    rules: ScanRules,
+    ordering: ScriptOrdering,
+    base_ref: String,
+    encoding: &'static Encoding,
+    index: RepositoryIndex,
+    source: ScriptSource,
+    linter: Linter,
+    /// Git status of changed files, read again once the repository changes on disk
+    statuses: Arc<RwLock<Option<HashMap<String, GitStatus>>>>,
 }
 
 impl Repository {
     /// Attempts to store path, if it's valid and the file exists.
-    /// Used for longer storage of paths.
+    /// Used for longer storage of paths. Paths to a zip archive, or to a folder inside one
+    /// such as `release.zip/migrations`, open the archive as a read-only repository.
     ///
     /// # Examples
     ///
@@ -37,18 +65,106 @@ impl Repository {
     pub fn new(root: PathBuf) -> Result<Self, RepositoryError> {
         let root_str = root.to_str().ok_or(RepositoryError::NotUTF8)?.to_string();
 
-        if root
-            .try_exists()
-            .map_err(|e| RepositoryError::IOError(e.to_string()))?
-        {
-            Ok(Self {
-                root,
-                root_str,
-                path: vec![],
-            })
-        } else {
-            Err(RepositoryError::DoesNotExist)
+        let source = ScriptSource::open(&root)?;
+
+        Ok(Self {
+            root,
+            root_str,
+            path: vec![],
+            rules: ScanRules::default(),
+            ordering: ScriptOrdering::default(),
+            base_ref: config::DEFAULT_BASE_REF.to_string(),
+            encoding: encoding_rs::WINDOWS_1252,
+            index: RepositoryIndex::default(),
+            source,
+            linter: Linter::default(),
+            statuses: Arc::default(),
+        })
+    }
+
+    pub fn with_rules(mut self, rules: ScanRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn with_ordering(mut self, ordering: ScriptOrdering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Git ref scripts are compared against when selecting what changed.
+    pub fn with_base_ref(mut self, base_ref: String) -> Self {
+        self.base_ref = base_ref;
+        self
+    }
+
+    pub fn base_ref(&self) -> &str {
+        &self.base_ref
+    }
+
+    /// Code page used for scripts that are neither UTF-8 nor UTF-16.
+    pub fn with_encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// Rules scripts are checked against when listed.
+    pub fn with_linter(mut self, linter: Linter) -> Self {
+        self.linter = linter;
+        self
+    }
+
+    pub fn linter(&self) -> &Linter {
+        &self.linter
+    }
+
+    /// Reads a script as text, detecting the encoding it is stored in.
+    pub fn read_script(&self, relative: &str) -> std::io::Result<Decoded> {
+        Ok(encoding::decode(
+            &self.source.read(relative)?,
+            self.encoding,
+        ))
+    }
+
+    pub fn source(&self) -> &ScriptSource {
+        &self.source
+    }
+
+    /// Starts indexing the repository in the background, queries walk the disk until it is done.
+    pub fn build_index(&self) -> std::thread::JoinHandle<()> {
+        self.index.build(
+            self.source.clone(),
+            self.root.clone(),
+            self.rules.clone(),
+            self.ordering,
+        )
+    }
+
+    /// Waits for the index started by [`Repository::build_index`] to be built.
+    pub fn wait_for_index(&self) {
+        self.index.wait()
+    }
+
+    /// Updates the index with paths reported as changed, returning the ones that really
+    /// changed on disk. Paths are relative to the repository root.
+    pub fn update_index(&self, changed: &[String]) -> Vec<String> {
+        let changed = self.index.update(
+            &self.source,
+            &self.root,
+            &self.rules,
+            self.ordering,
+            changed,
+        );
+        if !changed.is_empty() {
+            if let Ok(mut statuses) = self.statuses.write() {
+                *statuses = None;
+            }
         }
+        changed
     }
 
     pub fn base_as_str(&self) -> String {
@@ -87,180 +203,594 @@ impl Repository {
     }
 
     pub fn read_files_in_directory(&self) -> eyre::Result<Vec<String>> {
-        let current = self.current_as_path_buf();
-        let base = self.base_as_path_buf();
-        let entries = read_dir(current)?
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let path = entry.path();
-                let path_str = String::from(path.to_str().unwrap());
-                let file_name = path.file_name()?.to_str()?;
-
-                if file_name.starts_with('_') || file_name.starts_with('.') || path.is_dir() {
-                    return None;
-                }
-                if path.extension().and_then(|ext| ext.to_str()) == Some("sql") {
-                    let relative_path = path_str.replace(base.to_str().unwrap(), "");
-                    let fixed = relative_path.trim_start_matches(std::path::MAIN_SEPARATOR);
-
-                    Some(fixed.into())
-                } else {
-                    None
-                }
-            })
-            .collect();
+        Ok(self
+            .children(self.current_as_path_buf())
+            .into_iter()
+            .filter(|entry| entry.is_file && self.rules.is_script(&entry.relative))
+            .map(|entry| entry.relative)
+            .collect())
+    }
 
-        Ok(entries)
+    /// File names of the scripts directly in the directory at `relative`.
+    pub fn script_names_in(&self, relative: &str) -> Vec<String> {
+        self.children(self.base_as_path_buf().join(relative))
+            .into_iter()
+            .filter(|entry| entry.is_file && self.rules.is_script(&entry.relative))
+            .map(|entry| entry.name().to_string())
+            .collect()
     }
 
     pub fn get_children(&self, path: String) -> Vec<String> {
-        let base = self.base_as_path_buf();
-        let path = base.join(path);
-
-        if !path.is_dir() {
+        if !self.source.is_dir(&path) {
             return vec![];
         }
 
-        WalkDir::new(path)
-            .into_iter()
-            .filter_entry(|e| !is_hidden(e))
-            .filter_map(|e| e.ok())
-            .filter(|f| f.path().extension().map(|p| p == "sql").unwrap_or(false))
-            .map(|f| f.path().to_str().unwrap().to_string())
-            .map(|f| {
-                f.replace(base.to_str().unwrap(), "")
-                    .trim_start_matches(std::path::MAIN_SEPARATOR)
-                    .to_string()
-            })
+        self.walk_scripts(self.base_as_path_buf().join(path))
             .collect()
     }
 
     pub fn read_files_after(&self, from: &str) -> Vec<String> {
-        let current = self.current_as_path_buf();
-        let base = self.base_as_path_buf();
-        let target = current.join(from);
-        let target = target.as_path();
+        let target = self.current_as_path_buf().join(from);
+        let target = self.relative(&target).unwrap_or_default();
 
-        WalkDir::new(&base)
-            .sort_by_file_name()
-            .into_iter()
-            .filter_entry(|e| !is_hidden(e))
-            .filter_map(|e| e.ok())
-            .skip_while(|f| f.path() != target)
-            .filter(|f| {
-                f.file_type().is_file() && f.path().extension().unwrap_or_default() == "sql"
-            })
-            .filter_map(|f| {
-                let path = f.path();
-                let relative_path = path.strip_prefix(&base).ok()?;
-                relative_path.to_str().map(|f| f.to_string())
+        self.walk(self.base_as_path_buf())
+            .skip_while(|(f, _)| *f != target)
+            .filter(|(f, is_file)| *is_file && self.rules.is_script(f))
+            .map(|(f, _)| f)
+            .collect()
+    }
+
+    /// Lists every script from the start of the repository up to and including `to`,
+    /// which is a path relative to the repository root.
+    pub fn read_files_until(&self, to: &str) -> Vec<String> {
+        let mut files: Vec<String> = vec![];
+        for f in self.walk_scripts(self.base_as_path_buf()) {
+            let found = f == to;
+            files.push(f);
+            if found {
+                return files;
+            }
+        }
+
+        vec![]
+    }
+
+    /// Lists scripts added or modified in git after `since` and up to `until`,
+    /// or up to the working tree when `until` is not given, in repository order.
+    pub fn changed_since(
+        &self,
+        since: &str,
+        until: Option<&str>,
+    ) -> Result<Vec<String>, RepositoryError> {
+        let root = self
+            .source
+            .directory()
+            .ok_or_else(|| RepositoryError::Git("Archives are not tracked by git".to_string()))?;
+        let changed = git::changed_between(root, since, until)
+            .map_err(|e| RepositoryError::Git(e.message().to_string()))?;
+
+        Ok(self
+            .walk_scripts(self.base_as_path_buf())
+            .filter(|f| changed.contains(f))
+            .collect())
+    }
+
+    /// Rollback script paired with the script at `relative`, if there is one.
+    pub fn rollback_of(&self, relative: &str) -> Option<String> {
+        self.rules
+            .rollback_path(relative)
+            .filter(|rollback| match self.index.is_ready() {
+                true => self.index.get(rollback).is_some_and(|entry| entry.is_file),
+                false => self.source.is_file(rollback),
             })
+    }
+
+    pub fn rules(&self) -> &ScanRules {
+        &self.rules
+    }
+
+    pub fn ordering(&self) -> ScriptOrdering {
+        self.ordering
+    }
+
+    /// Whether `relative` is a script of the repository allowed by the scan rules.
+    pub fn contains_script(&self, relative: &str) -> bool {
+        let excluded = self.rules.is_within_excluded(relative);
+
+        let exists = match self.index.is_ready() {
+            true => self.index.get(relative).is_some_and(|entry| entry.is_file),
+            false => self.source.is_file(relative),
+        };
+
+        !excluded && self.rules.is_script(relative) && exists
+    }
+
+    /// Lists every repeatable script in the repository.
+    pub fn read_repeatable_scripts(&self) -> Vec<String> {
+        self.walk_scripts(self.base_as_path_buf())
+            .filter(|f| self.rules.is_repeatable(f))
             .collect()
     }
 
+    /// Reads dependencies declared by every script in the repository.
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        for entry in self.walk_entries(self.base_as_path_buf()) {
+            if !entry.is_file || !self.rules.is_script(&entry.relative) {
+                continue;
+            }
+            let dependencies = self
+                .inspect(&entry)
+                .map(|info| info.dependencies)
+                .unwrap_or_default();
+            graph.insert(&entry.relative, dependencies);
+        }
+        graph
+    }
+
+    /// Reads the script at `entry` for what the list shows about it, unless it was read
+    /// before and did not change since.
+    fn inspect(&self, entry: &IndexEntry) -> Option<ScriptInfo> {
+        if let Some(info) = self.index.inspected(entry) {
+            return Some(info);
+        }
+
+        let decoded = match self.read_script(&entry.relative) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                log::error!("Failed to read file {}: {}", entry.relative, e);
+                return None;
+            }
+        };
+        let info = ScriptInfo {
+            encoding: decoded.encoding.name(),
+            lint: self.linter.lint(&decoded.content),
+            dependencies: parse_dependencies(&decoded.content),
+        };
+        self.index.remember(entry, info.clone());
+        Some(info)
+    }
+
+    /// Git status of changed files, read once after every change of the repository.
+    /// Repositories outside of git, archives included, simply show no git status.
+    fn git_statuses(&self) -> HashMap<String, GitStatus> {
+        if let Some(statuses) = self.statuses.read().ok().and_then(|s| s.clone()) {
+            return statuses;
+        }
+
+        let statuses = self
+            .source
+            .directory()
+            .and_then(|root| git::statuses(root).ok())
+            .unwrap_or_default();
+        if let Ok(mut cached) = self.statuses.write() {
+            *cached = Some(statuses.clone());
+        }
+        statuses
+    }
+
     pub fn read_files_after_in_directory(&self, from: &str) -> eyre::Result<Vec<String>> {
+        Ok(self
+            .children(self.current_as_path_buf())
+            .into_iter()
+            .filter(|entry| entry.is_file && self.rules.is_script(&entry.relative))
+            .skip_while(|entry| entry.name() != from)
+            .map(|entry| entry.relative)
+            .collect())
+    }
+
+    pub fn read_entries_in_current_directory(&self) -> eyre::Result<Vec<ListEntry>> {
         let current = self.current_as_path_buf();
-        let base = self.base_as_path_buf();
-        let entries = read_dir(current)?
+        let statuses = self.git_statuses();
+        let directory_status = |relative: &str| {
+            let prefix = format!("{}{}", relative, std::path::MAIN_SEPARATOR);
+            statuses
+                .keys()
+                .any(|path| path.starts_with(&prefix))
+                .then_some(GitStatus::Modified)
+        };
+
+        Ok(self
+            .children(current)
+            .into_iter()
             .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let path = entry.path();
-                let path_str = String::from(path.to_str().unwrap());
-                let file_name = path.file_name()?.to_str()?;
-
-                if file_name.starts_with('_') || file_name.starts_with('.') || path.is_dir() {
-                    return None;
-                }
-
-                if path.extension().and_then(|ext| ext.to_str()) == Some("sql") {
-                    let relative_path = path_str.replace(base.to_str().unwrap(), "");
-                    let fixed = relative_path
-                        .trim_start_matches(std::path::MAIN_SEPARATOR)
-                        .to_owned();
-                    Some((fixed, file_name.to_owned()))
+                let file_name = entry.name().to_string();
+                let relative = entry.relative.clone();
+
+                // Check if it's a directory or a file matching the scan rules
+                if !entry.is_file {
+                    Some(ListEntry {
+                        is_directory: true,
+                        git: directory_status(&relative),
+                        relative_path: relative,
+                        name: file_name,
+                        selected: false,
+                        repeatable: false,
+                        encoding: None,
+                        rollback: false,
+                        lint: vec![],
+                        status: EntryStatus::Unknown,
+                    })
+                } else if self.rules.is_script(&relative) {
+                    let info = self.inspect(&entry)?;
+
+                    Some(ListEntry {
+                        is_directory: false,
+                        repeatable: self.rules.is_repeatable(&relative),
+                        git: statuses.get(&relative).copied(),
+                        rollback: self.rollback_of(&relative).is_some(),
+                        relative_path: relative,
+                        name: file_name,
+                        selected: false,
+                        encoding: Some(info.encoding),
+                        lint: info.lint,
+                        status: EntryStatus::Unknown,
+                    })
                 } else {
                     None
                 }
             })
-            .skip_while(|path| path.1 != from)
-            .map(|path| path.0)
+            .collect())
+    }
+
+    /// Walks `from` recursively, returning repository relative paths of all entries
+    /// along with whether they are files, skipping excluded directories altogether.
+    /// Uses the index once it is built.
+    fn walk(&self, from: PathBuf) -> impl Iterator<Item = (String, bool)> + '_ {
+        self.walk_entries(from)
+            .into_iter()
+            .map(|entry| (entry.relative, entry.is_file))
+    }
+
+    /// Same as [`Repository::walk`], keeping the whole entries.
+    fn walk_entries(&self, from: PathBuf) -> Vec<IndexEntry> {
+        let relative = self.relative(&from).unwrap_or_default();
+        self.index.subtree(&relative).unwrap_or_else(|| {
+            self.source
+                .scan(&self.root, &from, &self.rules, self.ordering, None)
+        })
+    }
+
+    /// Lists entries directly in the directory `from`, using the index once it is built.
+    fn children(&self, from: PathBuf) -> Vec<IndexEntry> {
+        let relative = self.relative(&from).unwrap_or_default();
+        self.index.children(&relative).unwrap_or_else(|| {
+            let mut entries =
+                self.source
+                    .scan(&self.root, &from, &self.rules, self.ordering, Some(1));
+            entries.retain(|entry| entry.relative != relative);
+            entries
+        })
+    }
+
+    /// Walks `from` recursively, returning repository relative paths of all scripts
+    /// allowed by the scan rules.
+    fn walk_scripts(&self, from: PathBuf) -> impl Iterator<Item = String> + '_ {
+        self.walk(from)
+            .filter(|(f, is_file)| *is_file && self.rules.is_script(f))
+            .map(|(f, _)| f)
+    }
+
+    fn relative(&self, path: &Path) -> Option<String> {
+        path.strip_prefix(&self.root)
+            .ok()?
+            .to_str()
+            .map(|f| f.to_string())
+    }
+}
+
+/// Decides which files in the repository are migration scripts.
+/// Patterns are globs matched against paths relative to the repository root.
+#[derive(Debug, Clone)]
+pub struct ScanRules {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    extensions: Vec<String>,
+    repeatable: GlobSet,
+    rollback_suffix: String,
+}
+
+impl ScanRules {
+    pub fn new(
+        include: &[String],
+        exclude: &[String],
+        extensions: &[String],
+    ) -> Result<Self, RepositoryError> {
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(build_glob_set(include)?)
+        };
+
+        Ok(Self {
+            include,
+            exclude: build_glob_set(exclude)?,
+            extensions: extensions
+                .iter()
+                .map(|ext| format!(".{}", ext.trim_start_matches('.').to_lowercase()))
+                .collect(),
+            repeatable: GlobSet::empty(),
+            rollback_suffix: "down".to_string(),
+        })
+    }
+
+    pub fn with_repeatable(mut self, repeatable: &[String]) -> Result<Self, RepositoryError> {
+        self.repeatable = build_glob_set(repeatable)?;
+        Ok(self)
+    }
+
+    pub fn with_rollback_suffix(mut self, suffix: &str) -> Self {
+        self.rollback_suffix = suffix.trim_matches('.').to_lowercase();
+        self
+    }
+
+    /// Configured exclusions add to [`config::DEFAULT_EXCLUDE`].
+    pub fn from_settings(settings: &config::Repository) -> Result<Self, RepositoryError> {
+        let exclude: Vec<String> = config::DEFAULT_EXCLUDE
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .chain(settings.exclude.iter().cloned())
             .collect();
 
-        Ok(entries)
+        Ok(
+            Self::new(&settings.include, &exclude, &settings.extensions)?
+                .with_repeatable(&settings.repeatable)?
+                .with_rollback_suffix(&settings.rollback_suffix),
+        )
     }
 
-    pub fn read_entries_in_current_directory(&self) -> eyre::Result<Vec<ListEntry>> {
-        let current = self.current_as_path_buf();
-        let base = self.base_as_path_buf();
-
-        match read_dir(current) {
-            Ok(entries) => {
-                let mut mapped: Vec<ListEntry> = entries
-                    .filter_map(|entry| {
-                        let entry = entry.ok()?;
-                        let path = entry.path();
-                        let path_str = String::from(path.to_str().unwrap());
-                        let file_name = path.file_name()?.to_str()?;
-
-                        if file_name.starts_with('_') || file_name.starts_with('.') {
-                            return None;
-                        }
-                        let relative_path = path_str.replace(base.to_str().unwrap(), "");
-                        let fixed = relative_path.trim_start_matches(std::path::MAIN_SEPARATOR);
-
-                        // Check if it's a directory or a file with .sql extension
-                        if path.is_dir() {
-                            Some(ListEntry {
-                                is_directory: true,
-                                relative_path: fixed.into(),
-                                name: file_name.into(),
-                                selected: false,
-                                status: EntryStatus::Unknown,
-                            })
-                        } else if path.extension().and_then(|ext| ext.to_str()) == Some("sql") {
-                            let content = fs::read_to_string(path_str);
-
-                            if content.is_err() {
-                                log::error!("Failed to read file: {}", content.unwrap_err());
-                                return None;
-                            }
-
-                            Some(ListEntry {
-                                is_directory: false,
-                                relative_path: fixed.into(),
-                                name: file_name.into(),
-                                selected: false,
-                                status: EntryStatus::Unknown,
-                            })
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
+    /// Extension new scripts are created with, the first one configured.
+    pub fn default_extension(&self) -> &str {
+        self.extensions
+            .first()
+            .map_or("sql", |ext| ext.trim_start_matches('.'))
+    }
+
+    /// Excluded entries are hidden, and for directories so is everything below them.
+    pub fn is_excluded(&self, relative: &str) -> bool {
+        !relative.is_empty() && self.exclude.is_match(normalize(relative))
+    }
+
+    /// Whether `relative` or any directory above it is excluded.
+    pub fn is_within_excluded(&self, relative: &str) -> bool {
+        Path::new(relative)
+            .ancestors()
+            .filter_map(|ancestor| ancestor.to_str())
+            .any(|ancestor| self.is_excluded(ancestor))
+    }
+
+    /// Compressed scripts such as `001.sql.gz` are scripts like their uncompressed counterparts.
+    pub fn is_script(&self, relative: &str) -> bool {
+        let normalized = normalize(relative);
+        let (script, _) = compression::split_extension(&normalized);
+        let lowercase = script.to_lowercase();
+
+        !self.is_excluded(relative)
+            && !self.is_rollback(relative)
+            && self.extensions.iter().any(|ext| lowercase.ends_with(ext))
+            && self
+                .include
+                .as_ref()
+                .is_none_or(|include| include.is_match(&normalized))
+    }
+
+    /// Rollback scripts undo the script they are paired with and are never run on their own.
+    pub fn is_rollback(&self, relative: &str) -> bool {
+        let (script, _) = compression::split_extension(relative);
+        let lowercase = script.to_lowercase();
+        self.extensions
+            .iter()
+            .any(|ext| lowercase.ends_with(&format!(".{}{}", self.rollback_suffix, ext)))
+    }
+
+    /// Path of the rollback paired with `relative` by naming convention, whether it exists or not.
+    /// Rollbacks of compressed scripts are compressed the same way.
+    pub fn rollback_path(&self, relative: &str) -> Option<String> {
+        let (script, compressed) = compression::split_extension(relative);
+        let lowercase = script.to_lowercase();
+        let ext = self
+            .extensions
+            .iter()
+            .find(|ext| lowercase.ends_with(*ext))?;
+        let (stem, extension) = script.split_at(script.len() - ext.len());
+
+        Some(format!(
+            "{}.{}{}{}",
+            stem, self.rollback_suffix, extension, compressed
+        ))
+    }
+
+    /// Repeatable scripts are applied again whenever their content changes,
+    /// after all versioned scripts.
+    pub fn is_repeatable(&self, relative: &str) -> bool {
+        self.repeatable.is_match(normalize(relative))
+    }
+}
+
+impl Default for ScanRules {
+    fn default() -> Self {
+        Self::from_settings(&config::Repository::default()).expect("Default scan rules are valid")
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet, RepositoryError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| RepositoryError::InvalidPattern(format!("{}: {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| RepositoryError::InvalidPattern(e.to_string()))
+}
+
+fn normalize(relative: &str) -> String {
+    relative.replace(std::path::MAIN_SEPARATOR, "/")
+}
 
-                mapped.sort();
+/// Strategy used to order scripts, both within a directory and across the repository.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptOrdering {
+    /// Plain string comparison, `file10.sql` comes before `file2.sql`
+    #[default]
+    Lexical,
+    /// Numbers inside names are compared by value, `file2.sql` comes before `file10.sql`
+    Natural,
+    /// Flyway style version prefix, `V1_2__y.sql` comes before `V1_10__x.sql`
+    VersionPrefix,
+    /// Date prefix such as `2024-01-15_x.sql` or `20240115T1030_x.sql`
+    DatePrefix,
+}
 
-                Ok(mapped)
+impl ScriptOrdering {
+    /// Compares two file or directory names.
+    /// Names the strategy cannot parse go after the ones it can, in natural order.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            ScriptOrdering::Lexical => a.cmp(b),
+            ScriptOrdering::Natural => natural_compare(a, b),
+            ScriptOrdering::VersionPrefix => compare_keys(version_prefix(a), version_prefix(b))
+                .then_with(|| natural_compare(a, b)),
+            ScriptOrdering::DatePrefix => {
+                compare_keys(date_prefix(a), date_prefix(b)).then_with(|| natural_compare(a, b))
             }
-            Err(e) => {
-                eprintln!("Failed to read directory: {}", e);
-                Ok(Vec::new())
+        }
+    }
+
+    /// Compares two repository relative paths component by component,
+    /// matching the order in which the repository is walked.
+    pub fn compare_paths(&self, a: &str, b: &str) -> Ordering {
+        let mut a_parts = Path::new(a).components();
+        let mut b_parts = Path::new(b).components();
+
+        loop {
+            match (a_parts.next(), b_parts.next()) {
+                (Some(a), Some(b)) => {
+                    let a = a.as_os_str().to_string_lossy();
+                    let b = b.as_os_str().to_string_lossy();
+                    match self.compare(&a, &b) {
+                        Ordering::Equal => continue,
+                        other => return other,
+                    }
+                }
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (None, None) => return Ordering::Equal,
             }
         }
     }
 }
 
-fn is_hidden(entry: &DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with('_') || s.starts_with('.'))
-        .unwrap_or(false)
+fn compare_keys<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn natural_compare(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a_chars);
+                let y = take_number(&mut b_chars);
+                let x_trimmed = x.trim_start_matches('0');
+                let y_trimmed = y.trim_start_matches('0');
+                let ordering = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (None, None) => return a.cmp(b),
+        }
+    }
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut number = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        number.push(c);
+    }
+    number
+}
+
+/// Parses `V1_10__name.sql`, `1.10__name.sql` or `v1_10_name.sql` into `[1, 10]`.
+pub(crate) fn version_prefix(name: &str) -> Option<Vec<u64>> {
+    let name = name.strip_prefix(['V', 'v']).unwrap_or(name);
+    let end = name.find("__").unwrap_or_else(|| {
+        name.find(|c: char| !c.is_ascii_digit() && c != '_' && c != '.')
+            .unwrap_or(name.len())
+    });
+
+    let parts: Vec<u64> = name[..end]
+        .split(['_', '.'])
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts)
+    }
+}
+
+/// Parses a leading `YYYYMMDD` date, with optional `-`, `_` or `.` between the parts,
+/// followed by an optional `HHMM` or `HHMMSS` time into a sortable number.
+fn date_prefix(name: &str) -> Option<u64> {
+    let mut digits = String::new();
+    let mut chars = name.chars().peekable();
+
+    for group in [4, 2, 2] {
+        if !digits.is_empty() {
+            chars.next_if(|c| matches!(c, '-' | '_' | '.'));
+        }
+        for _ in 0..group {
+            digits.push(chars.next_if(|c| c.is_ascii_digit())?);
+        }
+    }
+
+    let mut time = String::new();
+    let mut rest = chars.clone();
+    rest.next_if(|c| matches!(c, 'T' | 't' | '-' | '_' | '.'));
+    while time.len() < 6 {
+        match rest.next_if(|c| c.is_ascii_digit()) {
+            Some(c) => time.push(c),
+            None => break,
+        }
+    }
+    if time.len() != 4 && time.len() != 6 {
+        time.clear();
+    }
+
+    format!("{}{:0<6}", digits, time).parse().ok()
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::dependencies::DependencyError;
+    use std::fs;
     use std::{ffi::OsString, os::unix::ffi::OsStringExt};
 
     #[test]
@@ -268,7 +798,7 @@ mod test {
         let path = ".tests/repository/success";
         let r = Repository::new(PathBuf::from(path));
 
-        assert_eq!(true, r.is_ok());
+        assert!(r.is_ok());
         assert_eq!(String::from(path), r.unwrap().base_as_str())
     }
 
@@ -278,8 +808,8 @@ mod test {
 
         assert!(r.is_err());
         match r {
-            Err(RepositoryError::DoesNotExist) => assert!(true),
-            _ => assert!(false, "Expected RepositoryError::DoesNotExist"),
+            Err(RepositoryError::DoesNotExist) => {}
+            _ => panic!("Expected RepositoryError::DoesNotExist"),
         }
     }
 
@@ -291,10 +821,10 @@ mod test {
 
         let r = Repository::new(non_utf8_path);
 
-        assert_eq!(true, r.is_err());
+        assert!(r.is_err());
         match r {
-            Err(RepositoryError::NotUTF8) => assert!(true),
-            _ => assert!(false, "Expected RepositoryError::NotUTF8"),
+            Err(RepositoryError::NotUTF8) => {}
+            _ => panic!("Expected RepositoryError::NotUTF8"),
         }
     }
 
@@ -303,7 +833,7 @@ mod test {
         let path = ".tests/repository/dir1";
         let r = Repository::new(PathBuf::from(path));
 
-        assert_eq!(true, r.is_ok());
+        assert!(r.is_ok());
 
         let mut repository = r.unwrap();
         assert_eq!(String::from(path), repository.base_as_str());
@@ -331,7 +861,7 @@ mod test {
         let path = ".tests/repository/dir1";
         let r = Repository::new(PathBuf::from(path));
 
-        assert_eq!(true, r.is_ok());
+        assert!(r.is_ok());
 
         let mut repository = r.unwrap();
 
@@ -345,7 +875,7 @@ mod test {
         let path = ".tests/repository";
         let r = Repository::new(PathBuf::from(path));
 
-        assert_eq!(true, r.is_ok());
+        assert!(r.is_ok());
 
         let repository = r.unwrap();
 
@@ -358,7 +888,7 @@ mod test {
         let path = ".tests/repository/dir1";
         let r = Repository::new(PathBuf::from(path));
 
-        assert_eq!(true, r.is_ok());
+        assert!(r.is_ok());
 
         let repository = r.unwrap();
 
@@ -371,7 +901,7 @@ mod test {
         let path = ".tests/repository/dir1";
         let r = Repository::new(PathBuf::from(path));
 
-        assert_eq!(true, r.is_ok());
+        assert!(r.is_ok());
 
         let repository = r.unwrap();
 
@@ -384,7 +914,7 @@ mod test {
         let path = ".tests/repository/dir1";
         let r = Repository::new(PathBuf::from(path));
 
-        assert_eq!(true, r.is_ok());
+        assert!(r.is_ok());
 
         let mut repository = r.unwrap();
 
@@ -399,7 +929,7 @@ mod test {
         let path = ".tests/repository/dir1";
         let r = Repository::new(PathBuf::from(path));
 
-        assert_eq!(true, r.is_ok());
+        assert!(r.is_ok());
 
         let mut repository = r.unwrap();
 
@@ -414,7 +944,7 @@ mod test {
         let path = ".tests/repository/dir1";
         let r = Repository::new(PathBuf::from(path));
 
-        assert_eq!(true, r.is_ok());
+        assert!(r.is_ok());
 
         let mut repository = r.unwrap();
 
@@ -423,4 +953,534 @@ mod test {
         let children = repository.read_files_after("file6.sql");
         assert_eq!(2, children.len());
     }
+
+    #[test]
+    fn repository_select_until() {
+        let path = ".tests/repository/dir1";
+        let r = Repository::new(PathBuf::from(path));
+
+        assert!(r.is_ok());
+
+        let repository = r.unwrap();
+
+        let children = repository.read_files_until("dir3/file4.sql");
+        assert_eq!(
+            vec!["dir2/file2.sql", "dir3/file3.sql", "dir3/file4.sql"],
+            children
+        );
+    }
+
+    #[test]
+    fn repository_select_until_missing() {
+        let path = ".tests/repository/dir1";
+        let r = Repository::new(PathBuf::from(path));
+
+        assert!(r.is_ok());
+
+        let repository = r.unwrap();
+
+        let children = repository.read_files_until("dir3/nothing.sql");
+        assert!(children.is_empty());
+    }
+
+    #[test]
+    fn repository_rules_default() {
+        let repository = Repository::new(PathBuf::from(".tests/repository/rules")).unwrap();
+
+        let children = repository.get_children("".into());
+        assert_eq!(
+            vec![
+                "001.sql",
+                "002.rollback.sql",
+                "drafts/003.sql",
+                "sub/005.sql"
+            ],
+            children
+        );
+    }
+
+    #[test]
+    fn repository_rules_custom() {
+        let rules = ScanRules::new(
+            &[],
+            &[
+                "**/_*".into(),
+                "**/.*".into(),
+                "**/drafts".into(),
+                "*.rollback.sql".into(),
+            ],
+            &["sql".into(), "tsql".into()],
+        )
+        .unwrap();
+        let mut repository = Repository::new(PathBuf::from(".tests/repository/rules"))
+            .unwrap()
+            .with_rules(rules);
+
+        let children = repository.get_children("".into());
+        assert_eq!(vec!["001.sql", "sub/004.tsql", "sub/005.sql"], children);
+
+        let entries = repository
+            .read_entries_in_current_directory()
+            .expect("Cannot read entries");
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(vec!["001.sql", "sub"], names);
+
+        repository.open_directory("sub");
+        let files = repository
+            .read_files_in_directory()
+            .expect("Cannot read files");
+        assert_eq!(2, files.len());
+    }
+
+    #[test]
+    fn repository_rules_settings() {
+        let settings = config::Repository {
+            exclude: vec!["**/drafts".into()],
+            ..config::Repository::default()
+        };
+        let rules = ScanRules::from_settings(&settings).unwrap();
+        let repository = Repository::new(PathBuf::from(".tests/repository/rules"))
+            .unwrap()
+            .with_rules(rules);
+
+        // Hidden and underscored entries stay excluded along with the configured ones
+        let children = repository.get_children("".into());
+        assert_eq!(vec!["001.sql", "002.rollback.sql", "sub/005.sql"], children);
+    }
+
+    #[test]
+    fn repository_rules_include() {
+        let rules = ScanRules::new(&["sub/**".into()], &[], &["sql".into()]).unwrap();
+        let repository = Repository::new(PathBuf::from(".tests/repository/rules"))
+            .unwrap()
+            .with_rules(rules);
+
+        let children = repository.get_children("".into());
+        assert_eq!(vec!["sub/005.sql"], children);
+    }
+
+    #[test]
+    fn repository_rules_invalid() {
+        let rules = ScanRules::new(&["[".into()], &[], &["sql".into()]);
+        assert!(matches!(rules, Err(RepositoryError::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn repository_repeatable() {
+        let rules = ScanRules::default()
+            .with_repeatable(&["**/R__*".into(), "procs/**".into()])
+            .unwrap();
+        let repository = Repository::new(PathBuf::from(".tests/repository/repeatable"))
+            .unwrap()
+            .with_rules(rules);
+
+        assert_eq!(
+            vec![
+                "R__views.sql",
+                "procs/get_orders.sql",
+                "sub/R__functions.sql"
+            ],
+            repository.read_repeatable_scripts()
+        );
+
+        let entries = repository
+            .read_entries_in_current_directory()
+            .expect("Cannot read entries");
+        let repeatable: Vec<_> = entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.repeatable))
+            .collect();
+        assert_eq!(
+            vec![
+                ("001.sql", false),
+                ("R__views.sql", true),
+                ("procs", false),
+                ("sub", false)
+            ],
+            repeatable
+        );
+    }
+
+    #[test]
+    fn repository_dependencies() {
+        let repository = Repository::new(PathBuf::from(".tests/repository/dependencies")).unwrap();
+        let graph = repository.dependency_graph();
+
+        assert_eq!(
+            vec!["core/001_tables.sql", "core/002_keys.sql"],
+            graph.requirements("reports/001_summary.sql")
+        );
+        assert_eq!(
+            vec![DependencyError::Missing {
+                script: "reports/002_detail.sql".into(),
+                dependency: "core/003_missing.sql".into()
+            }],
+            graph.problems()
+        );
+    }
+
+    fn commit_all(repository: &git2::Repository, message: &str) {
+        let mut index = repository.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parent = repository.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repository
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn repository_changed_since() {
+        let root = std::env::temp_dir().join(format!("squealmate-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let migrations = root.join("migrations");
+        fs::create_dir_all(&migrations).unwrap();
+
+        let git = git2::Repository::init(&root).unwrap();
+        fs::write(root.join("README.md"), "readme").unwrap();
+        fs::write(migrations.join("001.sql"), "SELECT 1").unwrap();
+        commit_all(&git, "initial");
+        let head = git.head().unwrap().peel_to_commit().unwrap();
+        git.tag_lightweight("v1", head.as_object(), false).unwrap();
+
+        fs::write(migrations.join("001.sql"), "SELECT 11").unwrap();
+        fs::write(migrations.join("002.sql"), "SELECT 2").unwrap();
+        fs::write(root.join("README.md"), "changed").unwrap();
+        commit_all(&git, "second");
+        fs::write(migrations.join("003.sql"), "SELECT 3").unwrap();
+        fs::create_dir_all(migrations.join("sub")).unwrap();
+        fs::write(migrations.join("sub").join("004.sql"), "SELECT 4").unwrap();
+
+        let repository = Repository::new(migrations).unwrap();
+        let nested = Path::new("sub")
+            .join("004.sql")
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            vec!["001.sql", "002.sql", "003.sql", nested.as_str()],
+            repository.changed_since("v1", None).unwrap()
+        );
+        assert_eq!(
+            vec!["001.sql", "002.sql"],
+            repository.changed_since("v1", Some("HEAD")).unwrap()
+        );
+        assert!(matches!(
+            repository.changed_since("nonexistent", None),
+            Err(RepositoryError::Git(_))
+        ));
+
+        let entries = repository.read_entries_in_current_directory().unwrap();
+        let statuses: Vec<_> = entries.iter().map(|e| e.git).collect();
+        assert_eq!(
+            vec![
+                None,
+                None,
+                Some(git::GitStatus::Untracked),
+                Some(git::GitStatus::Modified)
+            ],
+            statuses
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn repository_index() {
+        let root = std::env::temp_dir().join(format!("squealmate-index-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("001.sql"), "SELECT 1").unwrap();
+        fs::write(root.join("sub").join("002.sql"), "SELECT 2").unwrap();
+
+        let repository = Repository::new(root.clone()).unwrap();
+        repository.build_index().join().unwrap();
+        assert!(repository.index.is_ready());
+
+        let names: Vec<_> = repository
+            .read_entries_in_current_directory()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(vec!["001.sql", "sub"], names);
+        assert!(repository.contains_script("sub/002.sql"));
+
+        // The index only learns about new files when told about them
+        fs::write(root.join("sub").join("003.sql"), "SELECT 3").unwrap();
+        assert!(!repository.contains_script("sub/003.sql"));
+        assert_eq!(
+            vec!["sub/003.sql"],
+            repository.update_index(&["sub/003.sql".into(), "001.sql".into()])
+        );
+        assert!(repository.contains_script("sub/003.sql"));
+        assert_eq!(
+            vec!["001.sql", "sub/002.sql", "sub/003.sql"],
+            repository.read_files_until("sub/003.sql")
+        );
+
+        fs::remove_file(root.join("001.sql")).unwrap();
+        assert_eq!(
+            vec!["001.sql"],
+            repository.update_index(&["001.sql".into()])
+        );
+        assert!(!repository.contains_script("001.sql"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn repository_inspection_cache() {
+        let root = std::env::temp_dir().join(format!("squealmate-inspect-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("001.sql"), "-- depends: 002.sql\nSELECT 1").unwrap();
+        fs::write(root.join("001.down.sql"), "SELECT 0").unwrap();
+        fs::write(root.join("002.sql"), "SELECT 2").unwrap();
+
+        let repository = Repository::new(root.clone()).unwrap();
+        repository.build_index();
+        repository.wait_for_index();
+        assert!(repository.index.is_ready());
+
+        let rollbacks: Vec<_> = repository
+            .read_entries_in_current_directory()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.rollback)
+            .collect();
+        assert_eq!(vec![true, false], rollbacks);
+        assert_eq!(
+            vec!["002.sql".to_string()],
+            repository.dependency_graph().dependencies_of("001.sql")
+        );
+
+        // Scripts are only read again once the index sees them change
+        fs::write(root.join("001.sql"), "-- depends: 003.sql\nSELECT 11").unwrap();
+        fs::remove_file(root.join("001.down.sql")).unwrap();
+        assert_eq!(
+            vec!["002.sql".to_string()],
+            repository.dependency_graph().dependencies_of("001.sql")
+        );
+        assert!(repository.rollback_of("001.sql").is_some());
+
+        repository.update_index(&["001.sql".into(), "001.down.sql".into()]);
+        assert_eq!(
+            vec!["003.sql".to_string()],
+            repository.dependency_graph().dependencies_of("001.sql")
+        );
+        assert!(repository.rollback_of("001.sql").is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn repository_encoding() {
+        let repository = Repository::new(PathBuf::from(".tests/repository/encoding")).unwrap();
+
+        let entries = repository.read_entries_in_current_directory().unwrap();
+        let encodings: Vec<_> = entries.iter().map(|e| e.encoding).collect();
+        assert_eq!(vec![Some("UTF-8"), Some("UTF-16LE")], encodings);
+
+        let decoded = repository.read_script("002_utf16.sql").unwrap();
+        assert_eq!("SELECT N'ž';\n", decoded.content);
+    }
+
+    #[test]
+    fn repository_compressed() {
+        let repository = Repository::new(PathBuf::from(".tests/repository/compressed")).unwrap();
+
+        let names: Vec<_> = repository
+            .read_entries_in_current_directory()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(
+            vec!["001.sql", "002_data.sql.gz", "003_data.sql.zst"],
+            names
+        );
+
+        let decoded = repository.read_script("002_data.sql.gz").unwrap();
+        assert_eq!("INSERT INTO t VALUES (2)", decoded.content);
+        assert_eq!(
+            Some("003_data.down.sql.zst".to_string()),
+            repository.rollback_of("003_data.sql.zst")
+        );
+        assert_eq!(
+            "DELETE FROM t",
+            repository
+                .read_script("003_data.down.sql.zst")
+                .unwrap()
+                .content
+        );
+    }
+
+    #[test]
+    fn repository_archive() {
+        let repository =
+            Repository::new(PathBuf::from(".tests/repository/release.zip/migrations")).unwrap();
+        assert!(repository.source().directory().is_none());
+
+        let names: Vec<_> = repository
+            .read_entries_in_current_directory()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(vec!["001.sql", "sub"], names);
+        assert_eq!(
+            vec!["001.sql", "sub/002.sql", "sub/003.sql.gz"],
+            repository.read_files_until("sub/003.sql.gz")
+        );
+        assert_eq!(
+            "SELECT 3",
+            repository.read_script("sub/003.sql.gz").unwrap().content
+        );
+        assert!(repository.contains_script("sub/002.sql"));
+        assert!(!repository.source().exists("migrations"));
+        assert!(matches!(
+            repository.changed_since("main", None),
+            Err(RepositoryError::Git(_))
+        ));
+
+        repository.build_index().join().unwrap();
+        assert_eq!(
+            vec!["sub/002.sql", "sub/003.sql.gz"],
+            repository.get_children("sub".into())
+        );
+
+        let whole = Repository::new(PathBuf::from(".tests/repository/release.zip")).unwrap();
+        let names: Vec<_> = whole
+            .read_entries_in_current_directory()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(vec!["migrations"], names);
+
+        assert!(matches!(
+            Repository::new(PathBuf::from(".tests/repository/release.zip/missing")),
+            Err(RepositoryError::DoesNotExist)
+        ));
+    }
+
+    #[test]
+    fn repository_rollback() {
+        let repository = Repository::new(PathBuf::from(".tests/repository/rollback")).unwrap();
+
+        assert_eq!(
+            vec!["001.sql", "002.SQL"],
+            repository.get_children("".into())
+        );
+        assert_eq!(
+            Some("001.down.sql".into()),
+            repository.rollback_of("001.sql")
+        );
+        assert_eq!(None, repository.rollback_of("002.SQL"));
+        assert_eq!(
+            Some("002.down.SQL".into()),
+            repository.rules().rollback_path("002.SQL")
+        );
+
+        let entries = repository.read_entries_in_current_directory().unwrap();
+        let rollbacks: Vec<_> = entries.iter().map(|e| e.rollback).collect();
+        assert_eq!(vec![true, false], rollbacks);
+    }
+
+    #[test]
+    fn ordering_natural() {
+        let ordering = ScriptOrdering::Natural;
+        assert_eq!(Ordering::Less, ordering.compare("file2.sql", "file10.sql"));
+        assert_eq!(Ordering::Less, ordering.compare("file02.sql", "file10.sql"));
+        assert_eq!(
+            Ordering::Greater,
+            ordering.compare("file2b.sql", "file2a.sql")
+        );
+        assert_eq!(
+            Ordering::Less,
+            ScriptOrdering::Lexical.compare("file10.sql", "file2.sql")
+        );
+    }
+
+    #[test]
+    fn ordering_version_prefix() {
+        let ordering = ScriptOrdering::VersionPrefix;
+        assert_eq!(
+            Ordering::Less,
+            ordering.compare("V1_2__y.sql", "V1_10__x.sql")
+        );
+        assert_eq!(
+            Ordering::Less,
+            ordering.compare("V1_10__x.sql", "V2__a.sql")
+        );
+        assert_eq!(
+            Ordering::Less,
+            ordering.compare("V1.2__y.sql", "V1_2_1__x.sql")
+        );
+        assert_eq!(Ordering::Less, ordering.compare("V9__x.sql", "readme.sql"));
+    }
+
+    #[test]
+    fn ordering_date_prefix() {
+        let ordering = ScriptOrdering::DatePrefix;
+        assert_eq!(
+            Ordering::Less,
+            ordering.compare("2024-01-15_b.sql", "20240116_a.sql")
+        );
+        assert_eq!(
+            Ordering::Less,
+            ordering.compare("20240115T0930_b.sql", "2024_01_15-1030_a.sql")
+        );
+        assert_eq!(
+            Ordering::Less,
+            ordering.compare("2024-01-15_b.sql", "2024-01-15T0001_a.sql")
+        );
+        assert_eq!(Ordering::Less, ordering.compare("20240115_x.sql", "x.sql"));
+    }
+
+    #[test]
+    fn ordering_paths() {
+        let ordering = ScriptOrdering::Natural;
+        assert_eq!(
+            Ordering::Less,
+            ordering.compare_paths("dir2/file10.sql", "dir10/file1.sql")
+        );
+        assert_eq!(
+            Ordering::Less,
+            ordering.compare_paths("dir2/file2.sql", "dir2/file10.sql")
+        );
+        assert_eq!(
+            Ordering::Less,
+            ordering.compare_paths("dir2", "dir2/file.sql")
+        );
+    }
+
+    #[test]
+    fn repository_ordering_natural() {
+        let repository = Repository::new(PathBuf::from(".tests/repository/ordering"))
+            .unwrap()
+            .with_ordering(ScriptOrdering::Natural);
+
+        let entries = repository
+            .read_entries_in_current_directory()
+            .expect("Cannot read entries");
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(vec!["file1.sql", "file2.sql", "file10.sql"], names);
+
+        let children = repository.read_files_after("file2.sql");
+        assert_eq!(vec!["file2.sql", "file10.sql"], children);
+    }
 }
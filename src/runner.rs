@@ -0,0 +1,177 @@
+use globset::Glob;
+use tokio::time::Instant;
+
+use encoding_rs::Encoding;
+
+use crate::{
+    db::split_batches, encoding, repository::Repository, script_memory::checksum,
+    source::ScriptSource, workspace::Workspace,
+};
+
+/// Outcome of executing a single script.
+#[derive(Debug)]
+pub struct Execution {
+    pub elapsed: u128,
+    /// Checksum of the script content, missing when the script could not be read
+    pub crc: Option<u32>,
+    /// What the database returned, see [`Database::execute_batches`](crate::db::Database::execute_batches)
+    pub result: Result<Vec<String>, String>,
+}
+
+/// Script as it is sent to the database.
+#[derive(Debug)]
+pub struct Prepared {
+    pub crc: u32,
+    pub encoding: &'static Encoding,
+    pub batches: Vec<String>,
+}
+
+/// Reads `file` from `source`, decoding and splitting it into batches.
+pub fn prepare(
+    source: &ScriptSource,
+    fallback: &'static Encoding,
+    file: &str,
+) -> std::io::Result<Prepared> {
+    let content = source.read(file)?;
+    let decoded = encoding::decode(&content, fallback);
+
+    Ok(Prepared {
+        crc: checksum(&content),
+        encoding: decoded.encoding,
+        batches: split_batches(&decoded.content),
+    })
+}
+
+/// Reads `file` from the workspace and executes it against the workspace database.
+pub async fn execute(workspace: &Workspace, file: &str) -> Execution {
+    let now = Instant::now();
+    let prepared = match prepare(&workspace.source, workspace.encoding, file) {
+        Ok(prepared) => prepared,
+        Err(err) => {
+            return Execution {
+                elapsed: now.elapsed().as_millis(),
+                crc: None,
+                result: Err(err.to_string()),
+            }
+        }
+    };
+
+    let result = workspace.connection.execute_batches(prepared.batches).await;
+
+    Execution {
+        elapsed: now.elapsed().as_millis(),
+        crc: Some(prepared.crc),
+        result: result.map_err(|err| err.to_string()),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SelectionError {
+    UnknownScript(String),
+    NoMatch(String),
+    InvalidPattern(String),
+}
+
+impl std::fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectionError::UnknownScript(script) => {
+                write!(f, "Script {} not found in repository", script)
+            }
+            SelectionError::NoMatch(pattern) => write!(f, "No script matches {}", pattern),
+            SelectionError::InvalidPattern(e) => write!(f, "Invalid pattern: {}", e),
+        }
+    }
+}
+
+/// Picks scripts of the repository given as paths or glob patterns, plus `after` and every
+/// script following it when set, in repository order and without duplicates.
+pub fn select(
+    repository: &Repository,
+    scripts: &[String],
+    after: Option<&str>,
+) -> Result<Vec<String>, SelectionError> {
+    let all = repository.get_children("".into());
+    let mut selected: Vec<String> = vec![];
+
+    for script in scripts {
+        let matching: Vec<String> = if is_pattern(script) {
+            let glob = Glob::new(script)
+                .map_err(|e| SelectionError::InvalidPattern(e.to_string()))?
+                .compile_matcher();
+            all.iter()
+                .filter(|s| glob.is_match(s.replace(std::path::MAIN_SEPARATOR, "/")))
+                .cloned()
+                .collect()
+        } else if repository.contains_script(script) {
+            vec![script.clone()]
+        } else {
+            return Err(SelectionError::UnknownScript(script.clone()));
+        };
+
+        if matching.is_empty() {
+            return Err(SelectionError::NoMatch(script.clone()));
+        }
+        selected.extend(matching);
+    }
+
+    if let Some(after) = after {
+        let position = all
+            .iter()
+            .position(|s| s == after)
+            .ok_or_else(|| SelectionError::UnknownScript(after.to_string()))?;
+        selected.extend(all[position..].iter().cloned());
+    }
+
+    Ok(all.into_iter().filter(|s| selected.contains(s)).collect())
+}
+
+fn is_pattern(script: &str) -> bool {
+    script.contains(['*', '?', '[', '{'])
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    #[test]
+    fn prepare_script() {
+        let source = ScriptSource::open(Path::new(".tests/repository/encoding")).unwrap();
+        let prepared = prepare(&source, encoding_rs::WINDOWS_1252, "002_utf16.sql").unwrap();
+        assert_eq!(encoding_rs::UTF_16LE, prepared.encoding);
+        assert_eq!(vec!["SELECT N'ž';\n"], prepared.batches);
+
+        let source = ScriptSource::open(Path::new(".tests/repository/compressed")).unwrap();
+        let prepared = prepare(&source, encoding_rs::WINDOWS_1252, "002_data.sql.gz").unwrap();
+        assert_eq!(vec!["INSERT INTO t VALUES (2)"], prepared.batches);
+        assert_eq!(checksum(b"INSERT INTO t VALUES (2)"), prepared.crc);
+    }
+
+    #[test]
+    fn select_scripts() {
+        let repository = Repository::new(PathBuf::from(".tests/repository/rules")).unwrap();
+
+        assert_eq!(
+            vec!["001.sql", "sub/005.sql"],
+            select(&repository, &["sub/005.sql".into(), "001.sql".into()], None).unwrap()
+        );
+        assert_eq!(
+            vec!["drafts/003.sql", "sub/005.sql"],
+            select(&repository, &["*/*.sql".into()], None).unwrap()
+        );
+        assert_eq!(
+            vec!["001.sql", "drafts/003.sql", "sub/005.sql"],
+            select(&repository, &["001.sql".into()], Some("drafts/003.sql")).unwrap()
+        );
+        assert_eq!(
+            Err(SelectionError::UnknownScript("sub/004.tsql".into())),
+            select(&repository, &["sub/004.tsql".into()], None)
+        );
+        assert_eq!(
+            Err(SelectionError::NoMatch("reports/**".into())),
+            select(&repository, &["reports/**".into()], None)
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    env, fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    config::Scaffold,
+    git,
+    repository::{version_prefix, Repository, ScriptOrdering},
+};
+
+/// Template of new scripts when none is configured.
+/// `{{name}}`, `{{description}}`, `{{author}}`, `{{date}}` and `{{ticket}}` are filled in.
+pub const DEFAULT_TEMPLATE: &str = "\
+-- {{description}}
+-- Script: {{name}}
+-- Author: {{author}}
+-- Date:   {{date}}
+-- Ticket: {{ticket}}
+
+SET XACT_ABORT ON;
+BEGIN TRANSACTION;
+
+
+
+COMMIT TRANSACTION;
+GO
+";
+
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const DEFAULT_EDITOR: &str = "vi";
+
+#[derive(Debug)]
+pub enum ScaffoldError {
+    EmptyDescription,
+    Archive,
+    Exists(String),
+    /// Directory is absolute or leads out of the repository
+    OutsideRepository(String),
+    Template(PathBuf, io::Error),
+    IOError(io::Error),
+}
+
+impl fmt::Display for ScaffoldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScaffoldError::EmptyDescription => write!(f, "Description cannot be empty"),
+            ScaffoldError::Archive => write!(f, "Scripts cannot be created in an archive"),
+            ScaffoldError::Exists(script) => write!(f, "Script {} already exists", script),
+            ScaffoldError::OutsideRepository(directory) => write!(
+                f,
+                "Directory {} must be relative to the repository root and stay inside it",
+                directory
+            ),
+            ScaffoldError::Template(path, e) => {
+                write!(f, "Cannot read template {} : {}", path.display(), e)
+            }
+            ScaffoldError::IOError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Values filled into the template.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues {
+    pub name: String,
+    pub description: String,
+    pub author: String,
+    pub date: String,
+    pub ticket: String,
+}
+
+pub fn render(template: &str, values: &TemplateValues) -> String {
+    template
+        .replace("{{name}}", &values.name)
+        .replace("{{description}}", &values.description)
+        .replace("{{author}}", &values.author)
+        .replace("{{date}}", &values.date)
+        .replace("{{ticket}}", &values.ticket)
+}
+
+/// Lowercase description with everything but letters and digits turned into single underscores.
+pub fn slug(description: &str) -> String {
+    let mut slug = String::new();
+    for c in description.trim().chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    slug.trim_end_matches('_').to_string()
+}
+
+/// Name of the script following the `existing` ones of a directory under `ordering`.
+/// Date prefixed scripts are named after `now`, versioned ones bump the last part of the
+/// highest version, and the others the highest leading number, keeping its width.
+pub fn next_name(
+    existing: &[String],
+    ordering: ScriptOrdering,
+    slug: &str,
+    extension: &str,
+    now: NaiveDateTime,
+) -> String {
+    let prefix = match ordering {
+        ScriptOrdering::DatePrefix => now.format("%Y%m%d%H%M%S_").to_string(),
+        ScriptOrdering::VersionPrefix => next_version(existing),
+        ScriptOrdering::Lexical | ScriptOrdering::Natural => next_number(existing),
+    };
+    format!("{}{}.{}", prefix, slug, extension)
+}
+
+fn next_version(existing: &[String]) -> String {
+    let Some((name, mut version)) = existing
+        .iter()
+        .filter_map(|name| Some((name, version_prefix(name)?)))
+        .max_by(|(_, a), (_, b)| a.cmp(b))
+    else {
+        return "V1__".to_string();
+    };
+
+    let letter = name.chars().next().filter(|c| matches!(c, 'V' | 'v'));
+    let separator = match name.split("__").next() {
+        Some(prefix) if prefix.contains('.') => ".",
+        _ => "_",
+    };
+    if let Some(last) = version.last_mut() {
+        *last += 1;
+    }
+
+    let version: Vec<String> = version.iter().map(|part| part.to_string()).collect();
+    format!(
+        "{}{}__",
+        letter.map(String::from).unwrap_or_default(),
+        version.join(separator)
+    )
+}
+
+fn next_number(existing: &[String]) -> String {
+    let highest = existing
+        .iter()
+        .filter_map(|name| {
+            let digits: String = name.chars().take_while(|c| c.is_ascii_digit()).collect();
+            let number = digits.parse::<u64>().ok()?;
+            let separator = name[digits.len()..]
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '_' | '-' | '.' | ' '))
+                .unwrap_or('_');
+            Some((number, digits.len(), separator))
+        })
+        .max_by_key(|(number, _, _)| *number);
+
+    match highest {
+        Some((number, width, separator)) => {
+            format!("{:0width$}{}", number + 1, separator, width = width)
+        }
+        None => "001_".to_string(),
+    }
+}
+
+/// Author of new scripts, configured or taken from git, falling back to the system user.
+fn author(repository: &Repository, settings: Option<&Scaffold>) -> String {
+    settings
+        .and_then(|s| s.author.clone())
+        .or_else(|| git::user_name(&repository.base_as_path_buf()))
+        .or_else(|| env::var("USER").ok())
+        .or_else(|| env::var("USERNAME").ok())
+        .unwrap_or_default()
+}
+
+/// Creates the next script in the directory at `directory`, relative to the repository root,
+/// filled from the template. Returns its path relative to the root and its full path.
+pub fn create(
+    repository: &Repository,
+    directory: &str,
+    description: &str,
+    ticket: &str,
+    settings: Option<&Scaffold>,
+) -> Result<(String, PathBuf), ScaffoldError> {
+    let slug = slug(description);
+    if slug.is_empty() {
+        return Err(ScaffoldError::EmptyDescription);
+    }
+    let Some(root) = repository.source().directory() else {
+        return Err(ScaffoldError::Archive);
+    };
+    let inside = Path::new(directory)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !inside {
+        return Err(ScaffoldError::OutsideRepository(directory.to_string()));
+    }
+
+    let template = match settings.and_then(|s| s.template.as_ref()) {
+        Some(path) => {
+            fs::read_to_string(path).map_err(|e| ScaffoldError::Template(path.clone(), e))?
+        }
+        None => DEFAULT_TEMPLATE.to_string(),
+    };
+
+    let now = chrono::Local::now();
+    let name = next_name(
+        &repository.script_names_in(directory),
+        repository.ordering(),
+        &slug,
+        repository.rules().default_extension(),
+        now.naive_local(),
+    );
+    let relative = Path::new(directory).join(&name);
+    let relative = relative.to_string_lossy().to_string();
+    let path = root.join(&relative);
+
+    let content = render(
+        &template,
+        &TemplateValues {
+            name: relative.clone(),
+            description: description.trim().to_string(),
+            author: author(repository, settings),
+            date: now.format("%Y-%m-%d").to_string(),
+            ticket: ticket.trim().to_string(),
+        },
+    );
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(ScaffoldError::IOError)?;
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => ScaffoldError::Exists(relative.clone()),
+            _ => ScaffoldError::IOError(e),
+        })?;
+    file.write_all(content.as_bytes())
+        .map_err(ScaffoldError::IOError)?;
+
+    Ok((relative, path))
+}
+
+/// Opens `path` in `$VISUAL` or `$EDITOR`, waiting until the editor is closed.
+pub fn open_in_editor(path: &Path) -> io::Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| DEFAULT_EDITOR.to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or(DEFAULT_EDITOR);
+
+    let status = Command::new(program).args(parts).arg(path).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "Editor {} exited with {}",
+            program, status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn create_outside_repository() {
+        let repository = Repository::new(PathBuf::from(".tests/repository/rules")).unwrap();
+        let outside = std::env::temp_dir().to_string_lossy().to_string();
+        for directory in ["..", "sub/../../escaped", outside.as_str()] {
+            assert!(matches!(
+                create(&repository, directory, "Add users", "", None),
+                Err(ScaffoldError::OutsideRepository(d)) if d == directory
+            ));
+        }
+    }
+
+    #[test]
+    fn slug_simple() {
+        assert_eq!("add_users_table", slug("  Add users table! "));
+        assert_eq!("přidání_tabulky", slug("Přidání tabulky"));
+        assert_eq!("", slug(" - "));
+    }
+
+    #[test]
+    fn next_name_orderings() {
+        let now = NaiveDate::from_ymd_opt(2024, 3, 5)
+            .unwrap()
+            .and_hms_opt(14, 7, 9)
+            .unwrap();
+        let next = |existing: &[&str], ordering| {
+            next_name(&names(existing), ordering, "users", "sql", now)
+        };
+
+        assert_eq!(
+            "010-users.sql",
+            next(&["002_a.sql", "009-b.sql"], ScriptOrdering::Natural)
+        );
+        assert_eq!(
+            "0100-users.sql",
+            next(&["0099-a.sql", "readme.sql"], ScriptOrdering::Lexical)
+        );
+        assert_eq!("001_users.sql", next(&[], ScriptOrdering::Natural));
+
+        assert_eq!(
+            "V1_11__users.sql",
+            next(
+                &["V1_2__a.sql", "V1_10__b.sql"],
+                ScriptOrdering::VersionPrefix
+            )
+        );
+        assert_eq!(
+            "2.4__users.sql",
+            next(&["2.3__a.sql"], ScriptOrdering::VersionPrefix)
+        );
+        assert_eq!("V1__users.sql", next(&[], ScriptOrdering::VersionPrefix));
+
+        assert_eq!(
+            "20240305140709_users.sql",
+            next(&["20240101_a.sql"], ScriptOrdering::DatePrefix)
+        );
+    }
+
+    #[test]
+    fn render_template() {
+        let values = TemplateValues {
+            name: "core/002_users.sql".to_string(),
+            description: "Add users".to_string(),
+            author: "Jane".to_string(),
+            date: "2024-03-05".to_string(),
+            ticket: "DB-42".to_string(),
+        };
+        let rendered = render(DEFAULT_TEMPLATE, &values);
+        assert!(rendered.starts_with("-- Add users\n-- Script: core/002_users.sql\n"));
+        assert!(rendered.contains("-- Author: Jane\n-- Date:   2024-03-05\n-- Ticket: DB-42\n"));
+        assert!(rendered.contains("BEGIN TRANSACTION;"));
+    }
+}
@@ -4,6 +4,7 @@ use crate::components::Component;
 pub(crate) enum Mode {
     FileChooser,
     ScriptRunner,
+    Orphans,
 }
 
 pub(crate) struct Screen {
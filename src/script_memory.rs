@@ -1,11 +1,21 @@
-use crate::{config::get_script_database, entries::EntryStatus};
+use crate::{
+    config::get_script_database, entries::EntryStatus, repository::ScanRules, source::ScriptSource,
+};
 use color_eyre::eyre::{self};
+use crc::{Crc, CRC_32_ISO_HDLC};
 use rusqlite::{named_params, Connection};
-use std::path::PathBuf;
+use std::{collections::HashSet, path::PathBuf};
 
 pub struct ScriptDatabaseRecord {
     crc: u32,
     result: bool,
+    manual: bool,
+}
+
+/// Computes the checksum stored alongside every script record.
+pub fn checksum(content: &[u8]) -> u32 {
+    let hasher = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+    hasher.checksum(content)
 }
 
 #[derive(Clone, Debug)]
@@ -14,8 +24,12 @@ pub struct ScriptDatabase {
 }
 
 impl ScriptDatabase {
-    pub async fn new() -> eyre::Result<Self> {
-        let filename = get_script_database();
+    pub async fn new(repository: &str, profile: Option<&str>) -> eyre::Result<Self> {
+        Self::open(get_script_database(repository, profile))
+    }
+
+    /// Opens the history stored in `filename`, creating or upgrading its schema.
+    pub(crate) fn open(filename: PathBuf) -> eyre::Result<Self> {
         let conn = Connection::open(filename.clone())?;
         conn.execute(
             "CREATE TABLE IF NOT EXISTS scripts (							
@@ -25,6 +39,18 @@ impl ScriptDatabase {
 					)",
             (), // empty list of parameters.
         )?;
+
+        // Databases created before manual marking was introduced lack the column
+        let has_manual = conn
+            .prepare("SELECT 1 FROM pragma_table_info('scripts') WHERE name = 'manual'")?
+            .exists(())?;
+        if !has_manual {
+            conn.execute(
+                "ALTER TABLE scripts ADD COLUMN manual INTEGER NOT NULL DEFAULT 0",
+                (),
+            )?;
+        }
+
         Ok(ScriptDatabase { db_name: filename })
     }
 
@@ -33,9 +59,9 @@ impl ScriptDatabase {
         // Prepare the statement and insert the records
         let mut stmt = conn.prepare(
             "
-						INSERT INTO scripts (name, crc, result) 
-						VALUES (:name, :crc, :result) ON CONFLICT(name) 
-         		DO UPDATE SET crc = excluded.crc, result = excluded.result
+						INSERT INTO scripts (name, crc, result, manual) 
+						VALUES (:name, :crc, :result, 0) ON CONFLICT(name) 
+         		DO UPDATE SET crc = excluded.crc, result = excluded.result, manual = 0
 						",
         )?;
         let res_text = if result { 1 } else { 0 };
@@ -44,6 +70,178 @@ impl ScriptDatabase {
         Ok(())
     }
 
+    /// Records the script as applied without running it.
+    pub fn mark_applied(&self, file: String, crc: u32) -> eyre::Result<()> {
+        let conn = Connection::open(self.db_name.clone())?;
+        let mut stmt = conn.prepare(
+            "
+						INSERT INTO scripts (name, crc, result, manual) 
+						VALUES (:name, :crc, 1, 1) ON CONFLICT(name) 
+         		DO UPDATE SET crc = excluded.crc, result = 1, manual = 1
+						",
+        )?;
+        stmt.execute(named_params! { ":name": file, ":crc": crc })?;
+
+        Ok(())
+    }
+
+    /// Forgets any history of the script, so it shows as never started again.
+    pub fn reset(&self, file: &str) -> eyre::Result<()> {
+        let conn = Connection::open(self.db_name.clone())?;
+        conn.execute("DELETE FROM scripts WHERE name = ?", [file])?;
+
+        Ok(())
+    }
+
+    /// Lists the names of the successful records stored with the given checksum.
+    pub fn find_by_crc(&self, crc: u32) -> eyre::Result<Vec<String>> {
+        let conn = Connection::open(self.db_name.clone())?;
+        let mut stmt =
+            conn.prepare("SELECT name FROM scripts WHERE crc = ? AND result = 1 ORDER BY name")?;
+        let names = stmt
+            .query_map([crc], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(names)
+    }
+
+    /// Lists the successful records with the given checksum whose scripts no longer exist
+    /// in `source`, meaning the script was most likely moved or renamed. More than one
+    /// candidate means scripts with identical content were removed, such as boilerplate,
+    /// and the history to carry over cannot be told apart.
+    pub fn find_moved(&self, source: &ScriptSource, crc: u32) -> eyre::Result<Vec<String>> {
+        Ok(self
+            .find_by_crc(crc)?
+            .into_iter()
+            .filter(|name| !source.exists(name))
+            .collect())
+    }
+
+    /// Status of the current content of `script` read from `source`, recognizing scripts
+    /// that were moved or renamed since they ran.
+    pub fn status_of(&self, source: &ScriptSource, script: &str) -> eyre::Result<EntryStatus> {
+        let crc = checksum(&source.read(script)?);
+        match self.get_file_status(script, &crc)? {
+            EntryStatus::NeverStarted => {
+                let candidates = self.find_moved(source, crc)?;
+                match candidates.is_empty() {
+                    true => Ok(EntryStatus::NeverStarted),
+                    false => Ok(EntryStatus::Moved(candidates)),
+                }
+            }
+            status => Ok(status),
+        }
+    }
+
+    /// Whether `script` has to run to bring the database up to date. Scripts applied under
+    /// the path they moved from count as applied. Versioned scripts changed after they were
+    /// applied are an error, only repeatable scripts run again when their content changes.
+    pub fn is_pending(
+        &self,
+        source: &ScriptSource,
+        rules: &ScanRules,
+        script: &str,
+    ) -> eyre::Result<bool> {
+        match self.status_of(source, script)? {
+            EntryStatus::Changed if !rules.is_repeatable(script) => Err(eyre::eyre!(
+                "{} changed after it was applied, add a new script instead of editing it",
+                script
+            )),
+            status => Ok(!status.is_applied()),
+        }
+    }
+
+    /// Lists records whose scripts no longer exist in `source`. Successful records with the
+    /// content of one of `scripts` that never ran under its own path are where that script
+    /// moved from, they are kept as the script counts as applied through them.
+    pub fn find_orphans(
+        &self,
+        source: &ScriptSource,
+        scripts: &[String],
+    ) -> eyre::Result<Vec<String>> {
+        let conn = Connection::open(self.db_name.clone())?;
+        let mut stmt = conn.prepare("SELECT name, crc, result FROM scripts ORDER BY name")?;
+        let records = stmt
+            .query_map((), |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, bool>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let recorded: HashSet<&str> = records.iter().map(|(name, _, _)| name.as_str()).collect();
+        let mut moved = HashSet::new();
+        for script in scripts.iter().filter(|s| !recorded.contains(s.as_str())) {
+            match source.read(script) {
+                Ok(content) => {
+                    moved.insert(checksum(&content));
+                }
+                Err(e) => log::error!("Error reading file {} : {}", script, e),
+            }
+        }
+
+        Ok(records
+            .iter()
+            .filter(|(name, crc, result)| {
+                !source.exists(name) && (!*result || !moved.contains(crc))
+            })
+            .map(|(name, _, _)| name.clone())
+            .collect())
+    }
+
+    /// Deletes the records of the given scripts.
+    pub fn prune(&self, files: &[String]) -> eyre::Result<()> {
+        let mut conn = Connection::open(self.db_name.clone())?;
+        let tx = conn.transaction()?;
+        for file in files {
+            tx.execute("DELETE FROM scripts WHERE name = ?", [file])?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Moves the records of the given scripts to the archive table,
+    /// so they no longer affect status but are kept for reference.
+    pub fn archive(&self, files: &[String]) -> eyre::Result<()> {
+        let mut conn = Connection::open(self.db_name.clone())?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS scripts_archive (
+							name  TEXT NOT NULL,
+							result INTEGER NOT NULL,
+							crc	 	INTEGER NOT NULL,
+							manual INTEGER NOT NULL,
+							archived TEXT NOT NULL
+					)",
+            (),
+        )?;
+        for file in files {
+            tx.execute(
+                "INSERT INTO scripts_archive (name, result, crc, manual, archived)
+						SELECT name, result, crc, manual, datetime('now') FROM scripts WHERE name = ?",
+                [file],
+            )?;
+            tx.execute("DELETE FROM scripts WHERE name = ?", [file])?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Moves the history of a script stored under `old` to `new`.
+    pub fn rekey(&self, old: &str, new: &str) -> eyre::Result<()> {
+        let conn = Connection::open(self.db_name.clone())?;
+        conn.execute(
+            "UPDATE OR REPLACE scripts SET name = :new WHERE name = :old",
+            named_params! { ":old": old, ":new": new },
+        )?;
+
+        Ok(())
+    }
+
     // pub fn find_many(&self, files: Vec<ListEntry>) -> eyre::Result<Vec<ListEntry>> {
     //     let names: Vec<String> = files
     //         .iter()
@@ -109,7 +307,7 @@ impl ScriptDatabase {
         let conn = Connection::open(self.db_name.clone())?;
 
         // Prepare the query to fetch the matching record for a single file
-        let query = "SELECT name, crc, result FROM scripts WHERE name = ?";
+        let query = "SELECT name, crc, result, manual FROM scripts WHERE name = ?";
 
         // Prepare the statement and query the database for the matching record
         let mut stmt = conn.prepare(query)?;
@@ -117,6 +315,7 @@ impl ScriptDatabase {
             Ok(ScriptDatabaseRecord {
                 crc: row.get::<_, u32>(1)?,     // Using String for CRC
                 result: row.get::<_, bool>(2)?, // Using bool for result
+                manual: row.get::<_, bool>(3)?,
             })
         })?;
 
@@ -124,7 +323,9 @@ impl ScriptDatabase {
         match rows.next() {
             Some(record) => match record {
                 Ok(record) => {
-                    if record.crc == *crc {
+                    if record.crc == *crc && record.manual {
+                        Ok(EntryStatus::Marked)
+                    } else if record.crc == *crc {
                         Ok(EntryStatus::Finished(record.result))
                     } else {
                         Ok(EntryStatus::Changed)
@@ -139,3 +340,162 @@ impl ScriptDatabase {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    /// History in a temporary database along with a directory of scripts,
+    /// both removed when the test passes.
+    fn fixture(name: &str) -> (PathBuf, ScriptDatabase, ScriptSource) {
+        let root =
+            std::env::temp_dir().join(format!("squealmate-memory-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("scripts").join("new")).unwrap();
+
+        let memory = ScriptDatabase::open(root.join("scripts.db")).unwrap();
+        let source = ScriptSource::open(&root.join("scripts")).unwrap();
+        (root, memory, source)
+    }
+
+    #[test]
+    fn find_by_crc_successful() {
+        let (root, memory, _) = fixture("crc");
+        memory.insert("a.sql".into(), 1, true).unwrap();
+        memory.insert("b.sql".into(), 1, false).unwrap();
+        memory.insert("c.sql".into(), 2, true).unwrap();
+        memory.mark_applied("d.sql".into(), 1).unwrap();
+
+        assert_eq!(vec!["a.sql", "d.sql"], memory.find_by_crc(1).unwrap());
+        assert!(memory.find_by_crc(3).unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn moved_scripts() {
+        let (root, memory, source) = fixture("moved");
+        fs::write(root.join("scripts").join("new").join("x.sql"), "SELECT 1").unwrap();
+        fs::write(root.join("scripts").join("y.sql"), "SELECT 1").unwrap();
+        let script = format!("new{}x.sql", std::path::MAIN_SEPARATOR);
+        let crc = checksum(b"SELECT 1");
+
+        // Failed runs and scripts still in place are not where it moved from
+        memory.insert("failed.sql".into(), crc, false).unwrap();
+        memory.insert("y.sql".into(), crc, true).unwrap();
+        assert!(memory.find_moved(&source, crc).unwrap().is_empty());
+        assert_eq!(
+            EntryStatus::NeverStarted,
+            memory.status_of(&source, &script).unwrap()
+        );
+
+        memory.insert("x.sql".into(), crc, true).unwrap();
+        assert_eq!(
+            EntryStatus::Moved(vec!["x.sql".into()]),
+            memory.status_of(&source, &script).unwrap()
+        );
+
+        // Identical content removed from several places is ambiguous
+        memory.insert("old.sql".into(), crc, true).unwrap();
+        assert_eq!(
+            EntryStatus::Moved(vec!["old.sql".into(), "x.sql".into()]),
+            memory.status_of(&source, &script).unwrap()
+        );
+
+        memory.rekey("x.sql", &script).unwrap();
+        assert_eq!(
+            EntryStatus::Finished(true),
+            memory.status_of(&source, &script).unwrap()
+        );
+        assert_eq!(
+            vec![script.as_str(), "old.sql", "y.sql"],
+            memory.find_by_crc(crc).unwrap()
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn pending_scripts() {
+        let (root, memory, source) = fixture("pending");
+        let rules = ScanRules::default();
+        let scripts = root.join("scripts");
+        fs::write(scripts.join("new").join("moved.sql"), "SELECT 1").unwrap();
+        fs::write(scripts.join("changed.sql"), "SELECT 2").unwrap();
+        fs::write(scripts.join("R__view.sql"), "SELECT 3").unwrap();
+        fs::write(scripts.join("fresh.sql"), "SELECT 4").unwrap();
+        let moved = format!("new{}moved.sql", std::path::MAIN_SEPARATOR);
+
+        memory
+            .insert("moved.sql".into(), checksum(b"SELECT 1"), true)
+            .unwrap();
+        memory.insert("changed.sql".into(), 1, true).unwrap();
+        memory.insert("R__view.sql".into(), 1, true).unwrap();
+
+        assert!(!memory.is_pending(&source, &rules, &moved).unwrap());
+        assert!(memory.is_pending(&source, &rules, "changed.sql").is_err());
+        assert!(memory.is_pending(&source, &rules, "R__view.sql").unwrap());
+        assert!(memory.is_pending(&source, &rules, "fresh.sql").unwrap());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn orphaned_history() {
+        let (root, memory, source) = fixture("orphans");
+        fs::write(root.join("scripts").join("kept.sql"), "SELECT 1").unwrap();
+        for name in ["archived.sql", "kept.sql", "pruned.sql"] {
+            memory.insert(name.into(), 1, true).unwrap();
+        }
+
+        assert_eq!(
+            vec!["archived.sql", "pruned.sql"],
+            memory.find_orphans(&source, &["kept.sql".into()]).unwrap()
+        );
+
+        memory.prune(&["pruned.sql".into()]).unwrap();
+        memory.archive(&["archived.sql".into()]).unwrap();
+        assert!(memory
+            .find_orphans(&source, &["kept.sql".into()])
+            .unwrap()
+            .is_empty());
+        assert_eq!(vec!["kept.sql"], memory.find_by_crc(1).unwrap());
+
+        // Archived records are kept aside, pruned ones are gone
+        let conn = Connection::open(root.join("scripts.db")).unwrap();
+        let archived: Vec<String> = conn
+            .prepare("SELECT name FROM scripts_archive")
+            .unwrap()
+            .query_map((), |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(vec!["archived.sql"], archived);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn moved_history_is_not_orphaned() {
+        let (root, memory, source) = fixture("orphans-moved");
+        fs::write(root.join("scripts").join("new").join("x.sql"), "SELECT 1").unwrap();
+        let script = format!("new{}x.sql", std::path::MAIN_SEPARATOR);
+        memory
+            .insert("x.sql".into(), checksum(b"SELECT 1"), true)
+            .unwrap();
+        memory.insert("gone.sql".into(), 2, true).unwrap();
+
+        let orphans = memory
+            .find_orphans(&source, std::slice::from_ref(&script))
+            .unwrap();
+        assert_eq!(vec!["gone.sql"], orphans);
+        memory.prune(&orphans).unwrap();
+
+        let status = memory.status_of(&source, &script).unwrap();
+        assert_eq!(EntryStatus::Moved(vec!["x.sql".into()]), status);
+        assert!(status.is_applied());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
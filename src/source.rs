@@ -0,0 +1,229 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf, MAIN_SEPARATOR, MAIN_SEPARATOR_STR},
+    sync::{Arc, Mutex},
+};
+
+use zip::ZipArchive;
+
+use crate::{
+    compression,
+    index::{self, IndexEntry},
+    repository::{RepositoryError, ScanRules, ScriptOrdering},
+};
+
+/// Where the scripts of a repository are read from. Paths are relative to the repository root.
+#[derive(Debug, Clone)]
+pub enum ScriptSource {
+    Directory(PathBuf),
+    /// Read-only folder inside a zip archive, e.g. `release.zip` or `release.zip/migrations`
+    Archive(Arc<Archive>),
+}
+
+impl ScriptSource {
+    pub fn open(root: &Path) -> Result<Self, RepositoryError> {
+        // Folder inside an archive, which does not exist on disk
+        for archive in root.ancestors().skip(1) {
+            if is_zip(archive) && archive.is_file() {
+                let inner = root
+                    .strip_prefix(archive)
+                    .ok()
+                    .and_then(|inner| inner.to_str())
+                    .ok_or(RepositoryError::NotUTF8)?;
+                let archive = Archive::open(root, archive, inner)?;
+                return Ok(Self::Archive(Arc::new(archive)));
+            }
+        }
+
+        if !root
+            .try_exists()
+            .map_err(|e| RepositoryError::IOError(e.to_string()))?
+        {
+            return Err(RepositoryError::DoesNotExist);
+        }
+
+        match is_zip(root) && root.is_file() {
+            true => Ok(Self::Archive(Arc::new(Archive::open(root, root, "")?))),
+            false => Ok(Self::Directory(root.to_path_buf())),
+        }
+    }
+
+    /// Folder on disk holding the scripts, `None` for archives.
+    pub fn directory(&self) -> Option<&Path> {
+        match self {
+            Self::Directory(root) => Some(root),
+            Self::Archive(_) => None,
+        }
+    }
+
+    /// Reads the content of a script, decompressed when the script is compressed.
+    pub fn read(&self, relative: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Directory(root) => compression::read(&root.join(relative)),
+            Self::Archive(archive) => {
+                compression::decompress(Path::new(relative), archive.read(relative)?)
+            }
+        }
+    }
+
+    pub fn is_file(&self, relative: &str) -> bool {
+        match self {
+            Self::Directory(root) => root.join(relative).is_file(),
+            Self::Archive(archive) => archive
+                .listing
+                .get(relative)
+                .is_some_and(|entry| entry.is_file),
+        }
+    }
+
+    /// Whether `relative` is a folder, the repository root being one.
+    pub fn is_dir(&self, relative: &str) -> bool {
+        match self {
+            Self::Directory(root) => root.join(relative).is_dir(),
+            Self::Archive(archive) => {
+                relative.is_empty()
+                    || archive
+                        .listing
+                        .get(relative)
+                        .is_some_and(|entry| !entry.is_file)
+            }
+        }
+    }
+
+    pub fn exists(&self, relative: &str) -> bool {
+        match self {
+            Self::Directory(root) => root.join(relative).exists(),
+            Self::Archive(archive) => relative.is_empty() || archive.listing.contains_key(relative),
+        }
+    }
+
+    /// Lists `from` and everything below it that is not excluded by `rules`, in repository order.
+    /// `from` is a path below `root`, the path the repository was opened with.
+    pub fn scan(
+        &self,
+        root: &Path,
+        from: &Path,
+        rules: &ScanRules,
+        ordering: ScriptOrdering,
+        max_depth: Option<usize>,
+    ) -> Vec<IndexEntry> {
+        match self {
+            Self::Directory(_) => index::scan(root, from, rules, ordering, max_depth),
+            Self::Archive(archive) => {
+                let Some(from) = from.strip_prefix(root).ok().and_then(|f| f.to_str()) else {
+                    return vec![];
+                };
+                let listing = archive
+                    .listing
+                    .iter()
+                    .map(|(relative, entry)| (relative.as_str(), entry.is_file, entry.size));
+                index::from_listing(listing, from, rules, ordering, max_depth)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ArchiveEntry {
+    is_file: bool,
+    size: u64,
+}
+
+/// Zip archive opened as a repository. Its listing is read once, files are read on demand.
+#[derive(Debug)]
+pub struct Archive {
+    /// Folder inside the archive holding the scripts, empty or ending with `/`
+    prefix: String,
+    /// Files and folders below the prefix, by repository relative path
+    listing: BTreeMap<String, ArchiveEntry>,
+    zip: Mutex<ZipArchive<File>>,
+}
+
+impl Archive {
+    fn open(root: &Path, path: &Path, inner: &str) -> Result<Self, RepositoryError> {
+        let file = File::open(path).map_err(|e| RepositoryError::IOError(e.to_string()))?;
+        let mut zip = ZipArchive::new(file).map_err(|e| RepositoryError::IOError(e.to_string()))?;
+
+        let inner = inner.replace(MAIN_SEPARATOR, "/");
+        let prefix = match inner.trim_matches('/') {
+            "" => String::new(),
+            inner => format!("{}/", inner),
+        };
+
+        let mut listing = BTreeMap::new();
+        for i in 0..zip.len() {
+            let file = zip
+                .by_index_raw(i)
+                .map_err(|e| RepositoryError::IOError(e.to_string()))?;
+            let Some(relative) = file.name().strip_prefix(&prefix) else {
+                continue;
+            };
+            let relative = relative.trim_end_matches('/');
+            if relative.is_empty() {
+                continue;
+            }
+
+            // Folders are not always stored in archives, they are implied by the files in them
+            let mut folder = relative;
+            while let Some((parent, _)) = folder.rsplit_once('/') {
+                listing
+                    .entry(parent.replace('/', MAIN_SEPARATOR_STR))
+                    .or_insert(ArchiveEntry {
+                        is_file: false,
+                        size: 0,
+                    });
+                folder = parent;
+            }
+
+            listing.insert(
+                relative.replace('/', MAIN_SEPARATOR_STR),
+                ArchiveEntry {
+                    is_file: file.is_file(),
+                    size: file.size(),
+                },
+            );
+        }
+
+        if !prefix.is_empty() && listing.is_empty() {
+            log::error!("Folder {} not found in {}", inner, root.display());
+            return Err(RepositoryError::DoesNotExist);
+        }
+
+        Ok(Self {
+            prefix,
+            listing,
+            zip: Mutex::new(zip),
+        })
+    }
+
+    fn read(&self, relative: &str) -> io::Result<Vec<u8>> {
+        let name = format!("{}{}", self.prefix, relative.replace(MAIN_SEPARATOR, "/"));
+        let mut zip = self
+            .zip
+            .lock()
+            .map_err(|_| io::Error::other("Archive is poisoned"))?;
+        let mut file = zip.by_name(&name).map_err(io::Error::other)?;
+
+        let mut content = vec![];
+        file.read_to_end(&mut content)?;
+        Ok(content)
+    }
+}
+
+/// Relative path written with `/` or `\` in the form repository paths are kept in,
+/// with the separator of the platform.
+pub fn native_path(relative: &str) -> String {
+    relative.replace(['/', '\\'], MAIN_SEPARATOR_STR)
+}
+
+/// Relative path in the form written to files shared between platforms, separated by `/`.
+pub fn portable_path(relative: &str) -> String {
+    relative.replace(MAIN_SEPARATOR, "/")
+}
+
+fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+}
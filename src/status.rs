@@ -0,0 +1,236 @@
+use serde::Serialize;
+
+use crate::entries::EntryStatus;
+
+/// How the status command prints its report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+/// Status of a script as reported by the status command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Applied,
+    Marked,
+    Pending,
+    Changed,
+    Failed,
+    Moved,
+    Unknown,
+}
+
+impl State {
+    /// Whether the script was applied. Moved scripts were, under the path they moved from,
+    /// and only need their history carried over to the new one.
+    pub fn is_done(&self) -> bool {
+        matches!(self, State::Applied | State::Marked | State::Moved)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            State::Applied => "applied",
+            State::Marked => "marked",
+            State::Pending => "pending",
+            State::Changed => "changed",
+            State::Failed => "failed",
+            State::Moved => "moved",
+            State::Unknown => "unknown",
+        }
+    }
+}
+
+impl From<&EntryStatus> for State {
+    fn from(status: &EntryStatus) -> Self {
+        match status {
+            EntryStatus::Finished(true) => State::Applied,
+            EntryStatus::Finished(false) => State::Failed,
+            EntryStatus::Marked => State::Marked,
+            EntryStatus::NeverStarted => State::Pending,
+            EntryStatus::Changed => State::Changed,
+            EntryStatus::Moved(_) => State::Moved,
+            EntryStatus::Unknown | EntryStatus::Directory => State::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScriptReport {
+    pub path: String,
+    pub status: State,
+    /// Paths the script may have run under before it was moved
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub moved_from: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Summary {
+    pub applied: usize,
+    pub marked: usize,
+    pub pending: usize,
+    pub changed: usize,
+    pub failed: usize,
+    pub moved: usize,
+    pub unknown: usize,
+}
+
+/// Status of the scripts of a repository against its database. The summary covers
+/// every script, the list only those needing to run or a rekey unless all of them were
+/// asked for.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatusReport {
+    pub repository: String,
+    pub summary: Summary,
+    pub scripts: Vec<ScriptReport>,
+}
+
+impl StatusReport {
+    pub fn new(repository: &str, statuses: Vec<(String, EntryStatus)>, all: bool) -> Self {
+        let mut summary = Summary::default();
+        let mut scripts = vec![];
+
+        for (path, status) in statuses {
+            let state = State::from(&status);
+            let moved_from = match status {
+                EntryStatus::Moved(candidates) => candidates,
+                _ => vec![],
+            };
+
+            *match state {
+                State::Applied => &mut summary.applied,
+                State::Marked => &mut summary.marked,
+                State::Pending => &mut summary.pending,
+                State::Changed => &mut summary.changed,
+                State::Failed => &mut summary.failed,
+                State::Moved => &mut summary.moved,
+                State::Unknown => &mut summary.unknown,
+            } += 1;
+
+            if all || !state.is_done() || state == State::Moved {
+                scripts.push(ScriptReport {
+                    path,
+                    status: state,
+                    moved_from,
+                });
+            }
+        }
+
+        Self {
+            repository: repository.to_string(),
+            summary,
+            scripts,
+        }
+    }
+
+    /// Whether any script still has to run. Moved scripts were applied and scripts whose
+    /// history cannot be read are not known to need a run.
+    pub fn has_pending(&self) -> bool {
+        let summary = &self.summary;
+        summary.pending + summary.changed + summary.failed > 0
+    }
+
+    pub fn to_table(&self) -> String {
+        let summary = &self.summary;
+        let mut table = format!(
+            "{}: {} applied, {} marked, {} pending, {} changed, {} failed, {} moved\n",
+            self.repository,
+            summary.applied,
+            summary.marked,
+            summary.pending,
+            summary.changed,
+            summary.failed,
+            summary.moved
+        );
+
+        for script in self.scripts.iter() {
+            table.push_str(&format!("{:<8} {}", script.status.label(), script.path));
+            match script.moved_from.as_slice() {
+                [] => {}
+                [old] => table.push_str(&format!(" (was {}, applied)", old)),
+                candidates => {
+                    table.push_str(&format!(" (was one of {}, applied)", candidates.join(", ")))
+                }
+            }
+            table.push('\n');
+        }
+        table
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Status report is serializable")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn report(all: bool) -> StatusReport {
+        StatusReport::new(
+            "core",
+            vec![
+                ("001.sql".into(), EntryStatus::Finished(true)),
+                ("002.sql".into(), EntryStatus::Marked),
+                ("003.sql".into(), EntryStatus::Changed),
+                (
+                    "sub/004.sql".into(),
+                    EntryStatus::Moved(vec!["004.sql".into()]),
+                ),
+                (
+                    "sub/006.sql".into(),
+                    EntryStatus::Moved(vec!["006.sql".into(), "old/006.sql".into()]),
+                ),
+                ("sub/005.sql".into(), EntryStatus::NeverStarted),
+            ],
+            all,
+        )
+    }
+
+    #[test]
+    fn status_table() {
+        let pending = report(false);
+        assert!(pending.has_pending());
+        assert_eq!(
+            "core: 1 applied, 1 marked, 1 pending, 1 changed, 0 failed, 2 moved\n\
+             changed  003.sql\n\
+             moved    sub/004.sql (was 004.sql, applied)\n\
+             moved    sub/006.sql (was one of 006.sql, old/006.sql, applied)\n\
+             pending  sub/005.sql\n",
+            pending.to_table()
+        );
+        assert_eq!(6, report(true).scripts.len());
+    }
+
+    #[test]
+    fn status_json() {
+        let json: serde_json::Value = serde_json::from_str(&report(false).to_json()).unwrap();
+        assert_eq!("core", json["repository"]);
+        assert_eq!(1, json["summary"]["pending"]);
+        assert_eq!("moved", json["scripts"][1]["status"]);
+        assert_eq!("004.sql", json["scripts"][1]["moved_from"][0]);
+        assert!(json["scripts"][0].get("moved_from").is_none());
+
+        let done = StatusReport::new("core", vec![("001.sql".into(), EntryStatus::Marked)], false);
+        assert!(!done.has_pending());
+
+        // Moved scripts are applied, they are only listed to carry their history over
+        let moved = StatusReport::new(
+            "core",
+            vec![
+                ("001.sql".into(), EntryStatus::Finished(true)),
+                (
+                    "sub/002.sql".into(),
+                    EntryStatus::Moved(vec!["002.sql".into()]),
+                ),
+                ("003.sql".into(), EntryStatus::Unknown),
+            ],
+            false,
+        );
+        assert!(!moved.has_pending());
+        assert_eq!(1, moved.summary.moved);
+        assert_eq!(2, moved.scripts.len());
+    }
+}
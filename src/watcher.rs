@@ -0,0 +1,120 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use color_eyre::eyre;
+use notify_debouncer_mini::{
+    new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, Debouncer,
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{action::Action, repository::ScanRules};
+
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+pub type RepositoryWatcher = Debouncer<RecommendedWatcher>;
+
+/// Watches the root of the repository named `name` and sends `Action::RepositoryChanged` with
+/// repository relative paths of everything that changed. Events are debounced, so a large checkout arrives
+/// as a single action instead of flooding the channel. Changes to excluded paths, such as
+/// writes git makes to `.git`, are not sent.
+///
+/// Watching stops when the returned watcher is dropped.
+pub fn watch_repository(
+    name: String,
+    root: PathBuf,
+    rules: ScanRules,
+    tx: UnboundedSender<Action>,
+) -> eyre::Result<RepositoryWatcher> {
+    let root = root.canonicalize()?;
+    let base = root.clone();
+
+    let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| match result {
+        Ok(events) => {
+            let changed = relevant_changes(&base, events.iter().map(|e| e.path.as_path()), &rules);
+            if changed.is_empty() {
+                return;
+            }
+
+            if let Err(error) = tx.send(Action::RepositoryChanged(name.clone(), changed)) {
+                log::error!("{}", error);
+            }
+        }
+        Err(error) => log::error!("Repository watcher error: {}", error),
+    })?;
+
+    debouncer.watcher().watch(&root, RecursiveMode::Recursive)?;
+
+    Ok(debouncer)
+}
+
+/// Paths below `base` relative to it, leaving out the root itself and excluded paths.
+fn relevant_changes<'a>(
+    base: &Path,
+    paths: impl Iterator<Item = &'a Path>,
+    rules: &ScanRules,
+) -> Vec<String> {
+    paths
+        .filter_map(|path| {
+            let relative = path.strip_prefix(base).ok()?;
+            relative.to_str().map(|f| f.to_string())
+        })
+        .filter(|relative| !relative.is_empty() && !rules.is_within_excluded(relative))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn excluded_changes() {
+        let base = Path::new("/repository");
+        let paths = [
+            base.join(".git").join("index"),
+            base.join("_drafts").join("001.sql"),
+            base.join("sub").join("002.sql"),
+            base.to_path_buf(),
+            PathBuf::from("/elsewhere/003.sql"),
+        ];
+
+        assert_eq!(
+            vec![format!("sub{}002.sql", std::path::MAIN_SEPARATOR)],
+            relevant_changes(
+                base,
+                paths.iter().map(|p| p.as_path()),
+                &ScanRules::default()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn watch_changes() {
+        let root = std::env::temp_dir().join(format!("squealmate-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join(".git")).unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let watcher =
+            watch_repository("default".into(), root.clone(), ScanRules::default(), tx).unwrap();
+
+        fs::write(root.join(".git").join("index"), "").unwrap();
+        fs::write(root.join("001.sql"), "SELECT 1").unwrap();
+
+        let action = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await;
+        assert_eq!(
+            Ok(Some(Action::RepositoryChanged(
+                "default".into(),
+                vec!["001.sql".into()]
+            ))),
+            action
+        );
+
+        drop(watcher);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
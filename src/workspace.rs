@@ -0,0 +1,57 @@
+use color_eyre::eyre;
+use encoding_rs::Encoding;
+
+use crate::{
+    db::Database,
+    entries::EntryStatus,
+    repository::ScanRules,
+    script_memory::{checksum, ScriptDatabase},
+    source::ScriptSource,
+};
+
+/// Repository opened in the session, along with the database its scripts run against
+/// and the memory of their previous runs.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub name: String,
+    pub source: ScriptSource,
+    pub connection: Database,
+    pub script_memory: ScriptDatabase,
+    /// Code page of scripts that are neither UTF-8 nor UTF-16
+    pub encoding: &'static Encoding,
+    pub rules: ScanRules,
+}
+
+impl Workspace {
+    /// Whether the current content of `script` was applied successfully or marked as applied.
+    pub fn is_applied(&self, script: &str) -> bool {
+        match self.source.read(script) {
+            Ok(content) => matches!(
+                self.script_memory
+                    .get_file_status(script, &checksum(&content)),
+                Ok(EntryStatus::Finished(true) | EntryStatus::Marked)
+            ),
+            Err(e) => {
+                log::error!("Error reading file {} : {}", script, e);
+                false
+            }
+        }
+    }
+
+    /// Status of the current content of `script` in the history, recognizing scripts
+    /// that were moved or renamed since they ran.
+    pub fn status_of(&self, script: &str) -> eyre::Result<EntryStatus> {
+        self.script_memory.status_of(&self.source, script)
+    }
+
+    /// Rollback script paired with `script`, if there is one.
+    pub fn rollback_of(&self, script: &str) -> Option<String> {
+        self.rules
+            .rollback_path(script)
+            .filter(|rollback| self.source.is_file(rollback))
+    }
+}
+
+pub fn find<'a>(workspaces: &'a [Workspace], name: &str) -> Option<&'a Workspace> {
+    workspaces.iter().find(|w| w.name == name)
+}